@@ -1,14 +1,32 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20261019_000001_add_graph_event_id;
+mod m20261019_000002_add_graph_level;
+mod m20261019_000003_create_withdrawal_tables;
+mod m20261019_000004_create_referral_tables;
+mod m20261019_000005_add_reward_status;
+mod m20261019_000006_add_graph_created_at_index;
+mod m20261019_000007_add_graph_hold_reason;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
+    fn migration_table_name() -> DynIden {
+        Alias::new("ygg_affiliate__seaql_migrations").into_iden()
+    }
+
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
         vec![
             Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261019_000001_add_graph_event_id::Migration),
+            Box::new(m20261019_000002_add_graph_level::Migration),
+            Box::new(m20261019_000003_create_withdrawal_tables::Migration),
+            Box::new(m20261019_000004_create_referral_tables::Migration),
+            Box::new(m20261019_000005_add_reward_status::Migration),
+            Box::new(m20261019_000006_add_graph_created_at_index::Migration),
+            Box::new(m20261019_000007_add_graph_hold_reason::Migration),
         ]
     }
 }
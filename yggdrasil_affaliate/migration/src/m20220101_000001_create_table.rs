@@ -54,6 +54,7 @@ impl MigrationTrait for Migration {
         ).await?;
         manager.create_index(
             Index::create()
+                .if_not_exists()
                 .table(AffGraph::Table)
                 .name("ygg_affiliate__from_index")
                 .col(AffGraph::From)
@@ -61,6 +62,7 @@ impl MigrationTrait for Migration {
         ).await?;
         manager.create_index(
             Index::create()
+                .if_not_exists()
                 .table(AffGraph::Table)
                 .name("ygg_affiliate__to_index")
                 .col(AffGraph::To)
@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum AffGraph {
+    #[sea_orm(iden = "ygg_affiliate__graph")]
+    Table,
+    EventId,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing edges predate event ids, so they get random ones.
+        manager.alter_table(
+            Table::alter()
+                .table(AffGraph::Table)
+                .add_column(ColumnDef::new(AffGraph::EventId).uuid().not_null().default(Expr::cust("gen_random_uuid()")))
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffGraph::Table)
+                .name("ygg_affiliate__event_id_unique_index")
+                .col(AffGraph::EventId)
+                .unique()
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__event_id_unique_index")
+                .table(AffGraph::Table)
+                .to_owned()
+        ).await?;
+        manager.alter_table(
+            Table::alter()
+                .table(AffGraph::Table)
+                .drop_column(AffGraph::EventId)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+}
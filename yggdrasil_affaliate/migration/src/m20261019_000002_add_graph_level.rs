@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum AffGraph {
+    #[sea_orm(iden = "ygg_affiliate__graph")]
+    Table,
+    EventId,
+    Level,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(AffGraph::Table)
+                .add_column(ColumnDef::new(AffGraph::Level).integer().not_null().default(1))
+                .to_owned()
+        ).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__event_id_unique_index")
+                .table(AffGraph::Table)
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffGraph::Table)
+                .name("ygg_affiliate__event_id_level_unique_index")
+                .col(AffGraph::EventId)
+                .col(AffGraph::Level)
+                .unique()
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__event_id_level_unique_index")
+                .table(AffGraph::Table)
+                .to_owned()
+        ).await?;
+        manager.get_connection().execute_unprepared(
+            "DELETE FROM ygg_affiliate__graph WHERE level > 1"
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffGraph::Table)
+                .name("ygg_affiliate__event_id_unique_index")
+                .col(AffGraph::EventId)
+                .unique()
+                .to_owned()
+        ).await?;
+        manager.alter_table(
+            Table::alter()
+                .table(AffGraph::Table)
+                .drop_column(AffGraph::Level)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum AffWithdrawal {
+    #[sea_orm(iden = "ygg_affiliate__withdrawal")]
+    Table,
+    Id,
+    UserId,
+    Amount,
+    Method,
+    Status,
+    ReviewedBy,
+    Note,
+    CreatedAt,
+    ReviewedAt,
+    PaidAt,
+}
+
+#[derive(DeriveIden)]
+enum AffLedger {
+    #[sea_orm(iden = "ygg_affiliate__ledger")]
+    Table,
+    Id,
+    UserId,
+    Kind,
+    Amount,
+    WithdrawalId,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(AffWithdrawal::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(AffWithdrawal::Id).integer().not_null().auto_increment().primary_key())
+                .col(ColumnDef::new(AffWithdrawal::UserId).uuid().not_null())
+                .col(ColumnDef::new(AffWithdrawal::Amount).float().not_null())
+                .col(ColumnDef::new(AffWithdrawal::Method).string().not_null())
+                .col(ColumnDef::new(AffWithdrawal::Status).string_len(16).not_null())
+                .col(ColumnDef::new(AffWithdrawal::ReviewedBy).uuid().null())
+                .col(ColumnDef::new(AffWithdrawal::Note).text().null())
+                .col(ColumnDef::new(AffWithdrawal::CreatedAt).timestamp().not_null())
+                .col(ColumnDef::new(AffWithdrawal::ReviewedAt).timestamp().null())
+                .col(ColumnDef::new(AffWithdrawal::PaidAt).timestamp().null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffWithdrawal::Table)
+                .name("ygg_affiliate__withdrawal_user_id_index")
+                .col(AffWithdrawal::UserId)
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffWithdrawal::Table)
+                .name("ygg_affiliate__withdrawal_status_index")
+                .col(AffWithdrawal::Status)
+                .to_owned()
+        ).await?;
+        manager.create_table(
+            Table::create()
+                .table(AffLedger::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(AffLedger::Id).integer().not_null().auto_increment().primary_key())
+                .col(ColumnDef::new(AffLedger::UserId).uuid().not_null())
+                .col(ColumnDef::new(AffLedger::Kind).string_len(16).not_null())
+                .col(ColumnDef::new(AffLedger::Amount).float().not_null())
+                .col(ColumnDef::new(AffLedger::WithdrawalId).integer().null())
+                .col(ColumnDef::new(AffLedger::CreatedAt).timestamp().not_null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffLedger::Table)
+                .name("ygg_affiliate__ledger_user_id_index")
+                .col(AffLedger::UserId)
+                .to_owned()
+        ).await?;
+        // Existing `withdrawn` values were set by hand and can't be traced to payouts,
+        // carry them over as opening entries so the derived value doesn't change.
+        manager.get_connection().execute_unprepared(
+            "INSERT INTO ygg_affiliate__ledger (user_id, kind, amount, created_at) \
+             SELECT user_id, 'payout', withdrawn, CURRENT_TIMESTAMP FROM ygg_affiliate__statistics WHERE withdrawn <> 0"
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__ledger_user_id_index")
+                .table(AffLedger::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(AffLedger::Table).to_owned()).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__withdrawal_status_index")
+                .table(AffWithdrawal::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__withdrawal_user_id_index")
+                .table(AffWithdrawal::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(AffWithdrawal::Table).to_owned()).await?;
+        Ok(())
+    }
+}
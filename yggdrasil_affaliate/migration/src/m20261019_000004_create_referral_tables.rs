@@ -0,0 +1,126 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum AffReferralCode {
+    #[sea_orm(iden = "ygg_affiliate__referral_code")]
+    Table,
+    Code,
+    UserId,
+    Campaign,
+    IsActive,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum AffReferralTouch {
+    #[sea_orm(iden = "ygg_affiliate__referral_touch")]
+    Table,
+    Id,
+    VisitorKey,
+    Code,
+    UserId,
+    Kind,
+    TouchedAt,
+}
+
+#[derive(DeriveIden)]
+enum AffAttribution {
+    #[sea_orm(iden = "ygg_affiliate__attribution")]
+    Table,
+    UserId,
+    InviterId,
+    Code,
+    TouchedAt,
+    AttributedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(AffReferralCode::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(AffReferralCode::Code).string().not_null().primary_key())
+                .col(ColumnDef::new(AffReferralCode::UserId).uuid().not_null())
+                .col(ColumnDef::new(AffReferralCode::Campaign).string().null())
+                .col(ColumnDef::new(AffReferralCode::IsActive).boolean().not_null().default(true))
+                .col(ColumnDef::new(AffReferralCode::CreatedAt).timestamp().not_null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffReferralCode::Table)
+                .name("ygg_affiliate__referral_code_user_id_index")
+                .col(AffReferralCode::UserId)
+                .to_owned()
+        ).await?;
+        manager.create_table(
+            Table::create()
+                .table(AffReferralTouch::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(AffReferralTouch::Id).integer().not_null().auto_increment().primary_key())
+                .col(ColumnDef::new(AffReferralTouch::VisitorKey).string().not_null())
+                .col(ColumnDef::new(AffReferralTouch::Code).string().not_null())
+                .col(ColumnDef::new(AffReferralTouch::UserId).uuid().not_null())
+                .col(ColumnDef::new(AffReferralTouch::Kind).string_len(16).not_null())
+                .col(ColumnDef::new(AffReferralTouch::TouchedAt).timestamp().not_null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffReferralTouch::Table)
+                .name("ygg_affiliate__referral_touch_visitor_index")
+                .col(AffReferralTouch::VisitorKey)
+                .col(AffReferralTouch::TouchedAt)
+                .to_owned()
+        ).await?;
+        manager.create_table(
+            Table::create()
+                .table(AffAttribution::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(AffAttribution::UserId).uuid().not_null().primary_key())
+                .col(ColumnDef::new(AffAttribution::InviterId).uuid().not_null())
+                .col(ColumnDef::new(AffAttribution::Code).string().not_null())
+                .col(ColumnDef::new(AffAttribution::TouchedAt).timestamp().not_null())
+                .col(ColumnDef::new(AffAttribution::AttributedAt).timestamp().not_null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffAttribution::Table)
+                .name("ygg_affiliate__attribution_inviter_id_index")
+                .col(AffAttribution::InviterId)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__attribution_inviter_id_index")
+                .table(AffAttribution::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(AffAttribution::Table).to_owned()).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__referral_touch_visitor_index")
+                .table(AffReferralTouch::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(AffReferralTouch::Table).to_owned()).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__referral_code_user_id_index")
+                .table(AffReferralCode::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(AffReferralCode::Table).to_owned()).await?;
+        Ok(())
+    }
+}
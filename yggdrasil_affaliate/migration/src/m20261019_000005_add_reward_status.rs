@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum AffGraph {
+    #[sea_orm(iden = "ygg_affiliate__graph")]
+    Table,
+    Status,
+    OrderReference,
+    ConfirmAfter,
+    ResolvedAt,
+}
+
+#[derive(DeriveIden)]
+enum AffStat {
+    #[sea_orm(iden = "ygg_affiliate__statistics")]
+    Table,
+    PendingTotal,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing rewards were already counted into `total`, so they start confirmed.
+        manager.alter_table(
+            Table::alter()
+                .table(AffGraph::Table)
+                .add_column(ColumnDef::new(AffGraph::Status).string_len(16).not_null().default("confirmed"))
+                .add_column(ColumnDef::new(AffGraph::OrderReference).string().null())
+                .add_column(ColumnDef::new(AffGraph::ConfirmAfter).timestamp().not_null().default(Expr::current_timestamp()))
+                .add_column(ColumnDef::new(AffGraph::ResolvedAt).timestamp().null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffGraph::Table)
+                .name("ygg_affiliate__order_reference_index")
+                .col(AffGraph::OrderReference)
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AffGraph::Table)
+                .name("ygg_affiliate__status_confirm_after_index")
+                .col(AffGraph::Status)
+                .col(AffGraph::ConfirmAfter)
+                .to_owned()
+        ).await?;
+        manager.alter_table(
+            Table::alter()
+                .table(AffStat::Table)
+                .add_column(ColumnDef::new(AffStat::PendingTotal).float().not_null().default(0.0))
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(AffStat::Table)
+                .drop_column(AffStat::PendingTotal)
+                .to_owned()
+        ).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__status_confirm_after_index")
+                .table(AffGraph::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__order_reference_index")
+                .table(AffGraph::Table)
+                .to_owned()
+        ).await?;
+        manager.alter_table(
+            Table::alter()
+                .table(AffGraph::Table)
+                .drop_column(AffGraph::Status)
+                .drop_column(AffGraph::OrderReference)
+                .drop_column(AffGraph::ConfirmAfter)
+                .drop_column(AffGraph::ResolvedAt)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+}
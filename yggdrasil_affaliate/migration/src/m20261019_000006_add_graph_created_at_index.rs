@@ -0,0 +1,35 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum AffGraph {
+    #[sea_orm(iden = "ygg_affiliate__graph")]
+    Table,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_index(
+            Index::create()
+                .table(AffGraph::Table)
+                .name("ygg_affiliate__created_at_index")
+                .col(AffGraph::CreatedAt)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_affiliate__created_at_index")
+                .table(AffGraph::Table)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+}
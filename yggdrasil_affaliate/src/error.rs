@@ -0,0 +1,77 @@
+use crate::repository::WithdrawalStatus;
+use sea_orm::{DbErr, TransactionError};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(Debug, Error)]
+pub enum AffiliateError {
+    #[error("database error")]
+    Database(#[from] DbErr),
+    /// The inviting user has no statistics record.
+    #[error("user {0} has no affiliate statistics record")]
+    UserNotFound(Uuid),
+    #[error("amount must be positive, got {0}")]
+    InvalidAmount(f32),
+    #[error("withdrawal of {amount} is below the minimum payout of {minimum}")]
+    BelowMinimumPayout {
+        amount: f32,
+        minimum: f32,
+    },
+    #[error("withdrawal of {requested} exceeds the available balance of {available}")]
+    InsufficientBalance {
+        requested: f32,
+        available: f32,
+    },
+    /// Referral codes are 3 to 32 letters, digits, `-` or `_`.
+    #[error("invalid referral code {0:?}")]
+    InvalidReferralCode(String),
+    #[error("referral code {0:?} is already taken")]
+    ReferralCodeTaken(String),
+    #[error("referral code {0:?} not found or inactive")]
+    ReferralCodeNotFound(String),
+    #[error("event rejected by fraud checks: {0}")]
+    FraudRejected(String),
+    #[error("event {0} has no held rewards")]
+    EventNotHeld(Uuid),
+    #[error("no rewards found for order {0:?}")]
+    OrderNotFound(String),
+    #[error("withdrawal {0} not found")]
+    WithdrawalNotFound(i32),
+    /// The withdrawal's status doesn't allow the requested transition.
+    #[error("withdrawal {id} is {status:?}")]
+    InvalidWithdrawalStatus {
+        id: i32,
+        status: WithdrawalStatus,
+    },
+}
+
+/// Failures to begin or commit surface as [AffiliateError::Database].
+impl From<TransactionError<AffiliateError>> for AffiliateError {
+    fn from(transaction_error: TransactionError<AffiliateError>) -> Self {
+        match transaction_error {
+            TransactionError::Connection(err) => AffiliateError::Database(err),
+            TransactionError::Transaction(err) => err,
+        }
+    }
+}
+
+impl AffiliateError {
+    /// Stable identifier for API responses, unaffected by changes to the messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AffiliateError::Database(_) => "affiliate.database",
+            AffiliateError::UserNotFound(_) => "affiliate.user_not_found",
+            AffiliateError::InvalidAmount(_) => "affiliate.invalid_amount",
+            AffiliateError::BelowMinimumPayout { .. } => "affiliate.below_minimum_payout",
+            AffiliateError::InsufficientBalance { .. } => "affiliate.insufficient_balance",
+            AffiliateError::InvalidReferralCode(_) => "affiliate.invalid_referral_code",
+            AffiliateError::ReferralCodeTaken(_) => "affiliate.referral_code_taken",
+            AffiliateError::ReferralCodeNotFound(_) => "affiliate.referral_code_not_found",
+            AffiliateError::FraudRejected(_) => "affiliate.fraud_rejected",
+            AffiliateError::EventNotHeld(_) => "affiliate.event_not_held",
+            AffiliateError::OrderNotFound(_) => "affiliate.order_not_found",
+            AffiliateError::WithdrawalNotFound(_) => "affiliate.withdrawal_not_found",
+            AffiliateError::InvalidWithdrawalStatus { .. } => "affiliate.invalid_withdrawal_status",
+        }
+    }
+}
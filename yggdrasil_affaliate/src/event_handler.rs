@@ -1,50 +1,181 @@
-use crate::repository::{AffiliateGraphData, AffiliateGraphDataBeforeCreate, AffiliateStatisticsData};
-use sea_orm::{ConnectionTrait, DbErr, DbErr::RecordNotFound, TransactionError, TransactionTrait};
+use crate::error::AffiliateError;
+use crate::fraud::{run_fraud_checks, FraudCheck, FraudVerdict};
+use crate::rate_rule::RateRules;
+use crate::referral::AttributionModel;
+use crate::repository::{
+    AffiliateGraphData, AffiliateGraphDataBeforeCreate, AffiliateStatisticsData, AffiliateStatisticsDataBeforeCreate,
+    RewardStatus,
+};
+use sea_orm::{ConnectionTrait, DatabaseTransaction, TransactionTrait};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
 use tracing::warn;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AffiliateEvent {
+    /// Unique per event, so delivering the same event again has no effect.
+    pub event_id: Uuid,
+    /// The inviting user, who receives the reward.
     pub from: Uuid,
+    /// The invited user.
     pub to: Uuid,
     pub raw_value: f32,
+    /// The order paying `raw_value`, so the rewards can be reversed when it's refunded.
+    #[serde(default)]
+    pub order_reference: Option<String>,
+    /// Selects the rate from [RateRules::product_rates].
+    #[serde(default)]
+    pub product_type: Option<String>,
 }
 
+#[derive(Clone)]
+pub struct AffiliateSettings {
+    /// Rate given to inviting users who don't have a statistics record yet.
+    pub default_rate: f32,
+    /// Adjusts the rate paid to the direct inviter, which is their own rate from the
+    /// statistics record otherwise.
+    pub rate_rules: RateRules,
+    /// Rates paid to the inviters further up the referral chain, starting at level 2.
+    pub upper_level_rates: Vec<f32>,
+    /// Smallest amount a user can request to withdraw.
+    pub minimum_payout: f32,
+    /// How long rewards stay pending before they can be withdrawn, giving refunds time to
+    /// reverse them. Rewards are confirmed right away when zero.
+    pub holding_period: chrono::Duration,
+    /// How long after a referral link is visited a signup is still credited to it.
+    pub attribution_window: chrono::Duration,
+    pub attribution_model: AttributionModel,
+    /// Run before each event is recorded, see [run_fraud_checks].
+    pub fraud_checks: Vec<Arc<dyn FraudCheck>>,
+}
+
+impl Default for AffiliateSettings {
+    fn default() -> Self {
+        Self {
+            default_rate: 0.0,
+            rate_rules: RateRules::default(),
+            upper_level_rates: Vec::new(),
+            minimum_payout: 0.0,
+            holding_period: chrono::Duration::zero(),
+            attribution_window: chrono::Duration::days(30),
+            attribution_model: AttributionModel::LastTouch,
+            fraud_checks: Vec::new(),
+        }
+    }
+}
+
+/// Record the invitation and reward the inviting user, and their own inviters up to
+/// the levels configured in `settings`.
+/// Events already recorded are skipped, so it's safe to retry after a failure.
+///
+/// Events held by fraud checks are recorded with [RewardStatus::Held] and not counted in the
+/// statistics until released with [release_held_event](crate::reward::release_held_event).
 pub async fn write_event_into_database(
     db: &(impl ConnectionTrait + TransactionTrait),
     event: &AffiliateEvent,
-) -> Result<(), DbErr> {
-    let to_user = AffiliateStatisticsData::find_by_id(db, event.from).await?;
-    if to_user.is_none() {
-        warn!("Yggdrasil Affiliate Module Error: A user ({}) doesn't have a statistics record but invites another user ({}).
-         This usually happen because of data inconsistency or bug.", event.to, event.from);
-        return Err(RecordNotFound("User does not exist".to_owned()));
+    settings: &AffiliateSettings,
+) -> Result<(), AffiliateError> {
+    let event = event.clone();
+    let settings = settings.clone();
+    let tr_result = db.transaction::<_, bool, AffiliateError>(|tx| {
+        Box::pin(async move {
+            write_event_in_transaction(tx, &event, &settings).await
+        })
+    }).await;
+    tr_result.map(|_| ()).map_err(AffiliateError::from)
+}
+
+/// Like [write_event_into_database], within a transaction of the caller so the event is
+/// recorded together with their own changes. Returns false if the event was already recorded.
+pub async fn write_event_in_transaction(
+    tx: &DatabaseTransaction,
+    event: &AffiliateEvent,
+    settings: &AffiliateSettings,
+) -> Result<bool, AffiliateError> {
+    let unheld_status = if settings.holding_period > chrono::Duration::zero() {
+        RewardStatus::Pending
+    } else {
+        RewardStatus::Confirmed
+    };
+    let now = chrono::Utc::now().naive_utc();
+    let confirm_after = now + settings.holding_period;
+    if !AffiliateGraphData::find_by_event_id(tx, event.event_id).await?.is_empty() {
+        return Ok(false);
+    }
+    // Locked before the fraud checks, so concurrent events of one inviter are checked in turn.
+    let mut from_user = AffiliateStatisticsData::find_by_id_for_update(tx, event.from).await?;
+    if from_user.is_none() {
+        warn!("Yggdrasil Affiliate Module: A user ({}) invites another user ({}) but doesn't have a statistics record.
+         Creating one with the default rate.", event.from, event.to);
+        AffiliateStatisticsData::create_if_missing(tx, AffiliateStatisticsDataBeforeCreate {
+            user_id: event.from,
+            rate: settings.default_rate,
+        }).await?;
+        from_user = AffiliateStatisticsData::find_by_id_for_update(tx, event.from).await?;
     }
-    let to_user = to_user.unwrap();
-    let rate = to_user.rate;
-    let raw_value = event.raw_value;
-    let reward = rate * raw_value;
+    let from_user = from_user.ok_or(AffiliateError::UserNotFound(event.from))?;
+    let (status, hold_reason) = match run_fraud_checks(tx, event, &settings.fraud_checks).await? {
+        FraudVerdict::Allow => (unheld_status, None),
+        FraudVerdict::Hold(reason) => {
+            warn!("Yggdrasil Affiliate Module: Held event ({}) for review: {}", event.event_id, reason);
+            (RewardStatus::Held, Some(reason))
+        }
+        FraudVerdict::Reject(reason) => {
+            warn!("Yggdrasil Affiliate Module: Rejected event ({}): {}", event.event_id, reason);
+            return Err(AffiliateError::FraudRejected(reason));
+        }
+    };
+    let rate = settings.rate_rules.rate_for(&from_user, event.product_type.as_deref(), now);
+    let reward = rate * event.raw_value;
     let graph_edge = AffiliateGraphDataBeforeCreate {
-        from: event.from.clone(),
-        to: event.to.clone(),
+        event_id: event.event_id,
+        from: event.from,
+        to: event.to,
+        level: 1,
         reward,
         rate,
+        status,
+        order_reference: event.order_reference.clone(),
+        confirm_after,
+        hold_reason: hold_reason.clone(),
     };
-    let tr_result = db.transaction::<_, (), DbErr>(|tx| {
-        Box::pin(async move {
-            AffiliateGraphData::create(tx, &graph_edge).await?;
-            AffiliateStatisticsData::on_invite(tx, &to_user, raw_value).await?;
-            Ok(())
-        })
-    }).await;
+    if !AffiliateGraphData::create_if_new_event(tx, &graph_edge).await? {
+        return Ok(false);
+    }
+    AffiliateStatisticsData::on_invite(tx, event.from, reward, status).await?;
 
-    fn flatten_error(transaction_error: TransactionError<DbErr>) -> DbErr {
-        match transaction_error {
-            TransactionError::Connection(err) => err,
-            TransactionError::Transaction(err) => err,
+    let mut visited = HashSet::from([event.to, event.from]);
+    let mut current = event.from;
+    for (index, &rate) in settings.upper_level_rates.iter().enumerate() {
+        let Some(inviter) = AffiliateGraphData::find_inviter(tx, current).await? else {
+            break;
+        };
+        if !visited.insert(inviter) {
+            warn!("Yggdrasil Affiliate Module: The referral chain of user ({}) contains a cycle at user ({}).
+             Stopped rewarding further levels.", event.to, inviter);
+            break;
         }
+        AffiliateStatisticsData::create_if_missing(tx, AffiliateStatisticsDataBeforeCreate {
+            user_id: inviter,
+            rate: settings.default_rate,
+        }).await?;
+        let reward = rate * event.raw_value;
+        AffiliateGraphData::create_if_new_event(tx, &AffiliateGraphDataBeforeCreate {
+            event_id: event.event_id,
+            from: inviter,
+            to: event.to,
+            level: index as i32 + 2,
+            reward,
+            rate,
+            status,
+            order_reference: event.order_reference.clone(),
+            confirm_after,
+            hold_reason: hold_reason.clone(),
+        }).await?;
+        AffiliateStatisticsData::add_reward(tx, inviter, reward, status).await?;
+        current = inviter;
     }
-
-    tr_result.map_err(flatten_error)
-}
\ No newline at end of file
+    Ok(true)
+}
@@ -0,0 +1,81 @@
+use crate::error::AffiliateError;
+use crate::event_handler::AffiliateEvent;
+use crate::repository::AffiliateGraphData;
+use async_trait::async_trait;
+use sea_orm::DatabaseTransaction;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FraudVerdict {
+    Allow,
+    /// Record the event but hold its rewards until an admin reviews them.
+    Hold(String),
+    /// Refuse to record the event.
+    Reject(String),
+}
+
+/// Inspects an [AffiliateEvent] before it's recorded, inside the transaction recording it.
+#[async_trait]
+pub trait FraudCheck: Send + Sync {
+    async fn check(&self, tx: &DatabaseTransaction, event: &AffiliateEvent) -> Result<FraudVerdict, AffiliateError>;
+}
+
+/// Holds events for a user already referred by someone else.
+pub struct DuplicateReferralCheck;
+
+#[async_trait]
+impl FraudCheck for DuplicateReferralCheck {
+    async fn check(&self, tx: &DatabaseTransaction, event: &AffiliateEvent) -> Result<FraudVerdict, AffiliateError> {
+        match AffiliateGraphData::find_inviter(tx, event.to).await? {
+            Some(inviter) if inviter != event.from => Ok(FraudVerdict::Hold(
+                format!("user {} was already referred by {}", event.to, inviter),
+            )),
+            _ => Ok(FraudVerdict::Allow),
+        }
+    }
+}
+
+/// Holds events once an inviter reaches `max_referrals` direct referral events within `window`.
+pub struct VelocityCheck {
+    pub max_referrals: u64,
+    pub window: chrono::Duration,
+}
+
+#[async_trait]
+impl FraudCheck for VelocityCheck {
+    async fn check(&self, tx: &DatabaseTransaction, event: &AffiliateEvent) -> Result<FraudVerdict, AffiliateError> {
+        let since = chrono::Utc::now().naive_utc() - self.window;
+        let recent = AffiliateGraphData::count_referrals_since(tx, event.from, since).await?;
+        if recent >= self.max_referrals {
+            return Ok(FraudVerdict::Hold(
+                format!("inviter {} has {} referral events in the last {}", event.from, recent, self.window),
+            ));
+        }
+        Ok(FraudVerdict::Allow)
+    }
+}
+
+/// Run every check, rejecting the event if any check rejects it and holding it with all
+/// reasons joined if any holds it. Self-referrals are always rejected.
+pub async fn run_fraud_checks(
+    tx: &DatabaseTransaction,
+    event: &AffiliateEvent,
+    checks: &[Arc<dyn FraudCheck>],
+) -> Result<FraudVerdict, AffiliateError> {
+    if event.from == event.to {
+        return Ok(FraudVerdict::Reject(format!("user {} referred themselves", event.from)));
+    }
+    let mut hold_reasons = Vec::new();
+    for check in checks {
+        match check.check(tx, event).await? {
+            FraudVerdict::Allow => {}
+            FraudVerdict::Hold(reason) => hold_reasons.push(reason),
+            reject @ FraudVerdict::Reject(_) => return Ok(reject),
+        }
+    }
+    if hold_reasons.is_empty() {
+        Ok(FraudVerdict::Allow)
+    } else {
+        Ok(FraudVerdict::Hold(hold_reasons.join("; ")))
+    }
+}
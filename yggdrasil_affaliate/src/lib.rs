@@ -1,2 +1,9 @@
 pub mod repository;
-pub mod event_handler;
\ No newline at end of file
+pub mod event_handler;
+pub mod error;
+pub mod fraud;
+pub mod rate_rule;
+pub mod referral;
+pub mod report;
+pub mod reward;
+pub mod withdrawal;
\ No newline at end of file
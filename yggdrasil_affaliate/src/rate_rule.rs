@@ -0,0 +1,142 @@
+use crate::repository::AffiliateStatisticsData;
+use std::collections::HashMap;
+
+/// Rate given once the inviter has at least `min_referrals` referrals or `min_earnings`
+/// confirmed rewards, whichever is reached first. Thresholds left `None` are ignored, a tier
+/// without any never applies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateTier {
+    pub min_referrals: Option<i32>,
+    pub min_earnings: Option<f32>,
+    pub rate: f32,
+}
+
+impl RateTier {
+    fn qualifies(&self, inviter: &AffiliateStatisticsData) -> bool {
+        self.min_referrals.is_some_and(|min_referrals| inviter.count_referrals >= min_referrals)
+            || self.min_earnings.is_some_and(|min_earnings| inviter.total >= min_earnings)
+    }
+}
+
+/// Rate given to every inviter during `[starts_at, ends_at)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CampaignRate {
+    pub name: String,
+    pub starts_at: chrono::NaiveDateTime,
+    pub ends_at: chrono::NaiveDateTime,
+    pub rate: f32,
+}
+
+/// Decides the rate of direct referral rewards.
+///
+/// An inviter gets the best of their own rate, the highest tier they qualify for and the
+/// running campaigns. Products with a rate in `product_rates` always use that rate instead.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RateRules {
+    pub tiers: Vec<RateTier>,
+    pub campaigns: Vec<CampaignRate>,
+    /// Fixed rates by product type, e.g. lower ones for low margin products.
+    pub product_rates: HashMap<String, f32>,
+}
+
+impl RateRules {
+    pub fn rate_for(
+        &self,
+        inviter: &AffiliateStatisticsData,
+        product_type: Option<&str>,
+        now: chrono::NaiveDateTime,
+    ) -> f32 {
+        if let Some(&rate) = product_type.and_then(|product_type| self.product_rates.get(product_type)) {
+            return rate;
+        }
+        let tier_rates = self.tiers.iter()
+            .filter(|tier| tier.qualifies(inviter))
+            .map(|tier| tier.rate);
+        let campaign_rates = self.campaigns.iter()
+            .filter(|campaign| campaign.starts_at <= now && now < campaign.ends_at)
+            .map(|campaign| campaign.rate);
+        tier_rates.chain(campaign_rates).fold(inviter.rate, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn inviter(count_referrals: i32, total: f32) -> AffiliateStatisticsData {
+        AffiliateStatisticsData {
+            user_id: Uuid::nil(),
+            total,
+            pending_total: 0.0,
+            withdrawn: 0.0,
+            count_referrals,
+            rate: 0.05,
+        }
+    }
+
+    fn at(day: u32) -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2026, 10, day).unwrap().and_hms_opt(0, 0, 0).unwrap()
+    }
+
+    fn rules() -> RateRules {
+        RateRules {
+            tiers: vec![
+                RateTier { min_referrals: Some(10), min_earnings: Some(500.0), rate: 0.08 },
+                RateTier { min_referrals: Some(50), min_earnings: None, rate: 0.12 },
+            ],
+            campaigns: vec![CampaignRate {
+                name: "autumn".to_owned(),
+                starts_at: at(10),
+                ends_at: at(20),
+                rate: 0.1,
+            }],
+            product_rates: HashMap::from([("gift_card".to_owned(), 0.01)]),
+        }
+    }
+
+    #[test]
+    fn own_rate_applies_below_every_tier() {
+        assert_eq!(rules().rate_for(&inviter(3, 100.0), None, at(1)), 0.05);
+    }
+
+    #[test]
+    fn tier_applies_when_either_threshold_is_reached() {
+        assert_eq!(rules().rate_for(&inviter(10, 0.0), None, at(1)), 0.08);
+        assert_eq!(rules().rate_for(&inviter(0, 500.0), None, at(1)), 0.08);
+    }
+
+    #[test]
+    fn unset_threshold_is_ignored() {
+        assert_eq!(rules().rate_for(&inviter(50, 0.0), None, at(1)), 0.12);
+        assert_eq!(rules().rate_for(&inviter(0, 100_000.0), None, at(1)), 0.08);
+    }
+
+    #[test]
+    fn tier_without_thresholds_never_applies() {
+        let rules = RateRules {
+            tiers: vec![RateTier { min_referrals: None, min_earnings: None, rate: 0.5 }],
+            ..RateRules::default()
+        };
+        assert_eq!(rules.rate_for(&inviter(100, 100_000.0), None, at(1)), 0.05);
+    }
+
+    #[test]
+    fn campaign_applies_within_its_period_only() {
+        assert_eq!(rules().rate_for(&inviter(0, 0.0), None, at(9)), 0.05);
+        assert_eq!(rules().rate_for(&inviter(0, 0.0), None, at(10)), 0.1);
+        assert_eq!(rules().rate_for(&inviter(0, 0.0), None, at(20)), 0.05);
+    }
+
+    #[test]
+    fn best_rate_wins() {
+        assert_eq!(rules().rate_for(&inviter(10, 0.0), None, at(15)), 0.1);
+        assert_eq!(rules().rate_for(&inviter(50, 0.0), None, at(15)), 0.12);
+    }
+
+    #[test]
+    fn product_rate_overrides_everything() {
+        assert_eq!(rules().rate_for(&inviter(50, 0.0), Some("gift_card"), at(15)), 0.01);
+        assert_eq!(rules().rate_for(&inviter(50, 0.0), Some("course"), at(15)), 0.12);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::AffiliateError;
+use crate::event_handler::AffiliateSettings;
+use crate::repository::{
+    AffiliateAttributionData, AffiliateAttributionDataBeforeCreate, AffiliateReferralCodeData,
+    AffiliateReferralCodeDataBeforeCreate, AffiliateReferralTouchData, AffiliateReferralTouchDataBeforeCreate,
+    ReferralTouchKind,
+};
+use rand::rngs::OsRng;
+use rand::Rng;
+use sea_orm::ConnectionTrait;
+use uuid::Uuid;
+
+/// Which touch gets the credit when a visitor came across several referral codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributionModel {
+    FirstTouch,
+    LastTouch,
+}
+
+const GENERATED_CODE_LENGTH: usize = 8;
+const GENERATED_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// Lowercase `code`, failing if it isn't 3 to 32 letters, digits, `-` or `_`.
+pub fn normalize_code(code: &str) -> Result<String, AffiliateError> {
+    let code = code.trim().to_lowercase();
+    let valid_chars = code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_chars || !(3..=32).contains(&code.len()) {
+        return Err(AffiliateError::InvalidReferralCode(code));
+    }
+    Ok(code)
+}
+
+fn generate_code() -> String {
+    let mut rng = OsRng;
+    (0..GENERATED_CODE_LENGTH)
+        .map(|_| char::from(GENERATED_CODE_ALPHABET[rng.gen_range(0..GENERATED_CODE_ALPHABET.len())]))
+        .collect()
+}
+
+/// Create a referral code for `user_id`, using the vanity `code` if given or a random one.
+pub async fn create_referral_code(
+    db: &impl ConnectionTrait,
+    user_id: Uuid,
+    code: Option<&str>,
+    campaign: Option<&str>,
+) -> Result<AffiliateReferralCodeData, AffiliateError> {
+    let vanity = code.map(normalize_code).transpose()?;
+    // Random codes rarely collide, retry a few times before giving up.
+    let attempts = if vanity.is_some() { 1 } else { 5 };
+    let mut code = String::new();
+    for _ in 0..attempts {
+        code = vanity.clone().unwrap_or_else(generate_code);
+        let inserted = AffiliateReferralCodeData::create_if_free(db, AffiliateReferralCodeDataBeforeCreate {
+            code: code.clone(),
+            user_id,
+            campaign: campaign.map(str::to_owned),
+        }).await?;
+        if inserted {
+            return AffiliateReferralCodeData::find_by_code(db, &code).await?
+                .ok_or(AffiliateError::ReferralCodeNotFound(code));
+        }
+    }
+    Err(AffiliateError::ReferralCodeTaken(code))
+}
+
+async fn find_active_code(
+    db: &impl ConnectionTrait,
+    code: &str,
+) -> Result<AffiliateReferralCodeData, AffiliateError> {
+    let code = normalize_code(code)?;
+    match AffiliateReferralCodeData::find_by_code(db, &code).await? {
+        Some(found) if found.is_active => Ok(found),
+        _ => Err(AffiliateError::ReferralCodeNotFound(code)),
+    }
+}
+
+/// Remember that the visitor identified by `visitor_key` opened a link with `code`.
+pub async fn record_click(
+    db: &impl ConnectionTrait,
+    visitor_key: &str,
+    code: &str,
+) -> Result<(), AffiliateError> {
+    let code = find_active_code(db, code).await?;
+    AffiliateReferralTouchData::create(db, AffiliateReferralTouchDataBeforeCreate {
+        visitor_key: visitor_key.to_owned(),
+        code: code.code,
+        user_id: code.user_id,
+        kind: ReferralTouchKind::Click,
+    }).await?;
+    Ok(())
+}
+
+/// Find the inviter of a user who just registered, from the code entered at signup and the
+/// links the visitor opened within the attribution window, and store the attribution.
+///
+/// The result is the `from` of the user's [AffiliateEvent](crate::event_handler::AffiliateEvent)s.
+/// Users can't refer themselves, and once attributed the inviter doesn't change.
+pub async fn resolve_inviter(
+    db: &impl ConnectionTrait,
+    visitor_key: &str,
+    signup_code: Option<&str>,
+    new_user_id: Uuid,
+    settings: &AffiliateSettings,
+) -> Result<Option<Uuid>, AffiliateError> {
+    if let Some(existing) = AffiliateAttributionData::find_by_user_id(db, new_user_id).await? {
+        return Ok(Some(existing.inviter_id));
+    }
+    if let Some(signup_code) = signup_code {
+        let code = find_active_code(db, signup_code).await?;
+        AffiliateReferralTouchData::create(db, AffiliateReferralTouchDataBeforeCreate {
+            visitor_key: visitor_key.to_owned(),
+            code: code.code,
+            user_id: code.user_id,
+            kind: ReferralTouchKind::Signup,
+        }).await?;
+    }
+    let since = chrono::Utc::now().naive_utc() - settings.attribution_window;
+    let touches = AffiliateReferralTouchData::find_by_visitor_since(db, visitor_key, since).await?;
+    let mut candidates = touches.into_iter().filter(|touch| touch.user_id != new_user_id);
+    let winner = match settings.attribution_model {
+        AttributionModel::FirstTouch => candidates.next(),
+        AttributionModel::LastTouch => candidates.next_back(),
+    };
+    let Some(winner) = winner else {
+        return Ok(None);
+    };
+    AffiliateAttributionData::create_if_missing(db, AffiliateAttributionDataBeforeCreate {
+        user_id: new_user_id,
+        inviter_id: winner.user_id,
+        code: winner.code,
+        touched_at: winner.touched_at,
+    }).await?;
+    // Another request may have attributed the user first, its result stands.
+    let attribution = AffiliateAttributionData::find_by_user_id(db, new_user_id).await?;
+    Ok(attribution.map(|attribution| attribution.inviter_id))
+}
@@ -0,0 +1,88 @@
+use crate::error::AffiliateError;
+use crate::repository::{
+    AffiliateAttributionData, AffiliateEarnings, AffiliateGraphData, AffiliateReferralTouchData, AffiliateStatisticsData,
+    EarningsBucket, LeaderboardMetric, ReportGranularity,
+};
+use sea_orm::ConnectionTrait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Clicks and the signups they converted into, for one referral code.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeConversion {
+    pub code: String,
+    pub clicks: i64,
+    pub signups: i64,
+}
+
+/// Everything about one affiliate over a time range, serializable for exports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AffiliateSummary {
+    pub user_id: Uuid,
+    pub from: chrono::NaiveDateTime,
+    pub to: chrono::NaiveDateTime,
+    pub earnings: Option<AffiliateEarnings>,
+    pub conversions: Vec<CodeConversion>,
+    /// Lifetime confirmed rewards, regardless of the range.
+    pub total: f32,
+    pub withdrawn: f32,
+}
+
+pub async fn earnings_over_time(
+    db: &impl ConnectionTrait,
+    user_id: Uuid,
+    granularity: ReportGranularity,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+) -> Result<Vec<EarningsBucket>, AffiliateError> {
+    Ok(AffiliateGraphData::earnings_by_period(db, user_id, granularity, from, to).await?)
+}
+
+pub async fn top_affiliates(
+    db: &impl ConnectionTrait,
+    metric: LeaderboardMetric,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+    limit: u64,
+) -> Result<Vec<AffiliateEarnings>, AffiliateError> {
+    Ok(AffiliateGraphData::leaderboard(db, metric, from, to, limit).await?)
+}
+
+/// Clicks and signups per code of `user_id` in `[from, to)`, ordered by code.
+pub async fn conversion_by_code(
+    db: &impl ConnectionTrait,
+    user_id: Uuid,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+) -> Result<Vec<CodeConversion>, AffiliateError> {
+    let mut conversions = BTreeMap::new();
+    for (code, clicks) in AffiliateReferralTouchData::count_clicks_by_code(db, user_id, from, to).await? {
+        conversions.entry(code).or_insert((0, 0)).0 = clicks;
+    }
+    for (code, signups) in AffiliateAttributionData::count_by_code(db, user_id, from, to).await? {
+        conversions.entry(code).or_insert((0, 0)).1 = signups;
+    }
+    Ok(conversions.into_iter()
+        .map(|(code, (clicks, signups))| CodeConversion { code, clicks, signups })
+        .collect())
+}
+
+pub async fn affiliate_summary(
+    db: &impl ConnectionTrait,
+    user_id: Uuid,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+) -> Result<AffiliateSummary, AffiliateError> {
+    let statistics = AffiliateStatisticsData::find_by_id(db, user_id).await?
+        .ok_or(AffiliateError::UserNotFound(user_id))?;
+    Ok(AffiliateSummary {
+        user_id,
+        from,
+        to,
+        earnings: AffiliateGraphData::earnings_of_user(db, user_id, from, to).await?,
+        conversions: conversion_by_code(db, user_id, from, to).await?,
+        total: statistics.total,
+        withdrawn: statistics.withdrawn,
+    })
+}
@@ -0,0 +1,96 @@
+use sea_orm::{
+    prelude::Expr, sea_query::OnConflict, ActiveModelBehavior, ActiveValue::Set, ColumnTrait, ConnectionTrait,
+    DbErr, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+use uuid::Uuid;
+
+/// Which user, through which code, brought a registered user in.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_affiliate__attribution")]
+pub struct Model {
+    /// The invited user, attributed at most once.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: Uuid,
+    #[sea_orm(indexed)]
+    pub inviter_id: Uuid,
+    pub code: String,
+    pub touched_at: chrono::NaiveDateTime,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub attributed_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type AffiliateAttributionData = Model;
+pub type AffiliateAttributionEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AffiliateAttributionDataBeforeCreate {
+    pub user_id: Uuid,
+    pub inviter_id: Uuid,
+    pub code: String,
+    pub touched_at: chrono::NaiveDateTime,
+}
+
+impl AffiliateAttributionData {
+    /// Insert the attribution unless the user already has one.
+    pub async fn create_if_missing(
+        db: &impl ConnectionTrait,
+        data: AffiliateAttributionDataBeforeCreate,
+    ) -> Result<(), DbErr> {
+        Entity::insert(ActiveModel {
+            user_id: Set(data.user_id),
+            inviter_id: Set(data.inviter_id),
+            code: Set(data.code),
+            touched_at: Set(data.touched_at),
+            attributed_at: Set(chrono::Utc::now().naive_utc()),
+        })
+            .on_conflict(OnConflict::column(Column::UserId).do_nothing().to_owned())
+            .exec_without_returning(db).await?;
+        Ok(())
+    }
+
+    pub async fn find_by_user_id(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find_by_id(user_id).one(db).await
+    }
+
+    /// Signups attributed to each of `inviter_id`'s codes in `[from, to)`.
+    pub async fn count_by_code(
+        db: &impl ConnectionTrait,
+        inviter_id: Uuid,
+        from: chrono::NaiveDateTime,
+        to: chrono::NaiveDateTime,
+    ) -> Result<Vec<(String, i64)>, DbErr> {
+        Entity::find()
+            .select_only()
+            .column(Column::Code)
+            .column_as(Column::UserId.count(), "signups")
+            .filter(Column::InviterId.eq(inviter_id))
+            .filter(Column::AttributedAt.gte(from))
+            .filter(Column::AttributedAt.lt(to))
+            .group_by(Column::Code)
+            .into_tuple()
+            .all(db).await
+    }
+
+    pub async fn find_by_inviter_id(
+        db: &impl ConnectionTrait,
+        inviter_id: Uuid,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::InviterId.eq(inviter_id))
+            .order_by_desc(Column::AttributedAt)
+            .offset(offset)
+            .limit(limit)
+            .all(db).await
+    }
+}
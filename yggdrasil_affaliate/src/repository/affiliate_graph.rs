@@ -1,21 +1,99 @@
 use sea_orm::{
-    prelude::Expr, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait,
-    DbErr, DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait,
-    EnumIter, PrimaryKeyTrait, QueryFilter, QuerySelect,
+    prelude::Expr, sea_query::{OnConflict, StringLen}, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DbErr, DeleteResult, DeriveActiveEnum, DeriveEntityModel, DerivePrimaryKey,
+    DeriveRelation, EntityTrait, EnumIter, FromQueryResult, PaginatorTrait, PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect,
 };
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+pub enum RewardStatus {
+    /// Held until `confirm_after`, counted in the inviter's `pending_total`.
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    /// Counted in the inviter's `total` and withdrawable.
+    #[sea_orm(string_value = "confirmed")]
+    Confirmed,
+    /// Taken back, e.g. because the order was refunded.
+    #[sea_orm(string_value = "reversed")]
+    Reversed,
+    /// Flagged by a fraud check and not counted until reviewed, see `hold_reason`.
+    #[sea_orm(string_value = "held")]
+    Held,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportGranularity {
+    Day,
+    Week,
+    Month,
+}
+
+impl ReportGranularity {
+    fn date_trunc_unit(self) -> &'static str {
+        match self {
+            ReportGranularity::Day => "day",
+            ReportGranularity::Week => "week",
+            ReportGranularity::Month => "month",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LeaderboardMetric {
+    Revenue,
+    Referrals,
+}
+
+/// Rewards of one user created within one period. Held and reversed rewards aren't included.
+#[derive(Debug, Clone, PartialEq, FromQueryResult, Serialize, Deserialize)]
+pub struct EarningsBucket {
+    pub period_start: chrono::NaiveDateTime,
+    pub reward: f32,
+    pub events: i64,
+}
+
+/// Rewards of one user within a time range. `referrals` counts distinct users invited directly.
+#[derive(Debug, Clone, PartialEq, FromQueryResult, Serialize, Deserialize)]
+pub struct AffiliateEarnings {
+    pub user_id: Uuid,
+    pub reward: f32,
+    pub pending_reward: f32,
+    pub reversed_reward: f32,
+    pub held_reward: f32,
+    pub events: i64,
+    pub referrals: i64,
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "ygg_affiliate__graph")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
+    /// Id of the [AffiliateEvent](crate::event_handler::AffiliateEvent) that created this edge.
+    /// (`event_id`, `level`) is unique.
+    #[sea_orm(indexed)]
+    pub event_id: Uuid,
     #[sea_orm(indexed)]
     pub from: Uuid,
     #[sea_orm(indexed)]
     pub to: Uuid,
+    /// 1 when `from` invited `to` directly, 2 when `from` invited the inviter of `to`, and so on.
+    pub level: i32,
     pub reward: f32,
     pub rate: f32,
+    #[sea_orm(indexed)]
+    pub status: RewardStatus,
+    /// The order the reward was paid for, used to reverse it on refunds.
+    #[sea_orm(indexed)]
+    pub order_reference: Option<String>,
+    /// When a pending reward becomes confirmed.
+    pub confirm_after: chrono::NaiveDateTime,
+    /// When the reward was confirmed or reversed.
+    pub resolved_at: Option<chrono::NaiveDateTime>,
+    /// Why fraud checks held the reward.
+    pub hold_reason: Option<String>,
     #[sea_orm(default_expr = "Expr::current_timestamp()")]
     pub created_at: chrono::NaiveDateTime,
 }
@@ -30,10 +108,16 @@ pub type AffiliateGraphEntity = Entity;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct AffiliateGraphDataBeforeCreate {
+    pub event_id: Uuid,
     pub from: Uuid,
     pub to: Uuid,
+    pub level: i32,
     pub reward: f32,
     pub rate: f32,
+    pub status: RewardStatus,
+    pub order_reference: Option<String>,
+    pub confirm_after: chrono::NaiveDateTime,
+    pub hold_reason: Option<String>,
 }
 
 impl AffiliateGraphData {
@@ -42,14 +126,221 @@ impl AffiliateGraphData {
         data: &AffiliateGraphDataBeforeCreate,
     ) -> Result<Self, DbErr> {
         ActiveModel {
+            event_id: Set(data.event_id),
             from: Set(data.from),
             to: Set(data.to),
+            level: Set(data.level),
             rate: Set(data.rate),
             reward: Set(data.reward),
+            status: Set(data.status),
+            order_reference: Set(data.order_reference.clone()),
+            confirm_after: Set(data.confirm_after),
+            hold_reason: Set(data.hold_reason.clone()),
             ..Default::default()
         }.insert(db).await
     }
 
+    /// Insert the edge unless one already exists for its event and level.
+    /// Returns whether a row was inserted.
+    pub async fn create_if_new_event(
+        db: &impl ConnectionTrait,
+        data: &AffiliateGraphDataBeforeCreate,
+    ) -> Result<bool, DbErr> {
+        let result = Entity::insert(ActiveModel {
+            event_id: Set(data.event_id),
+            from: Set(data.from),
+            to: Set(data.to),
+            level: Set(data.level),
+            rate: Set(data.rate),
+            reward: Set(data.reward),
+            status: Set(data.status),
+            order_reference: Set(data.order_reference.clone()),
+            confirm_after: Set(data.confirm_after),
+            hold_reason: Set(data.hold_reason.clone()),
+            ..Default::default()
+        })
+            .on_conflict(OnConflict::columns([Column::EventId, Column::Level]).do_nothing().to_owned())
+            .exec_without_returning(db).await?;
+        Ok(result > 0)
+    }
+
+    pub async fn find_by_event_id(
+        db: &impl ConnectionTrait,
+        event_id: Uuid,
+    ) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::EventId.eq(event_id))
+            .order_by_asc(Column::Level)
+            .all(db).await
+    }
+
+    pub async fn find_by_order_reference(
+        db: &impl ConnectionTrait,
+        order_reference: &str,
+    ) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::OrderReference.eq(order_reference))
+            .order_by_asc(Column::Id)
+            .all(db).await
+    }
+
+    pub async fn find_held(
+        db: &impl ConnectionTrait,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::Status.eq(RewardStatus::Held))
+            .order_by_asc(Column::Id)
+            .offset(offset)
+            .limit(limit)
+            .all(db).await
+    }
+
+    /// Number of level 1 edges from `from` created at or after `since`.
+    pub async fn count_referrals_since(
+        db: &impl ConnectionTrait,
+        from: Uuid,
+        since: chrono::NaiveDateTime,
+    ) -> Result<u64, DbErr> {
+        Entity::find()
+            .filter(Column::From.eq(from))
+            .filter(Column::Level.eq(1))
+            .filter(Column::CreatedAt.gte(since))
+            .count(db).await
+    }
+
+    /// Pending rewards whose holding period ended at `now`, oldest first.
+    pub async fn find_pending_due(
+        db: &impl ConnectionTrait,
+        now: chrono::NaiveDateTime,
+        limit: u64,
+    ) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::Status.eq(RewardStatus::Pending))
+            .filter(Column::ConfirmAfter.lte(now))
+            .order_by_asc(Column::ConfirmAfter)
+            .limit(limit)
+            .all(db).await
+    }
+
+    /// Move the edge from `from_status` to `to_status`. Returns false when its status has
+    /// changed meanwhile, so concurrent callers can't both apply the transition.
+    pub async fn transition_status(
+        db: &impl ConnectionTrait,
+        id: i32,
+        from_status: RewardStatus,
+        to_status: RewardStatus,
+    ) -> Result<bool, DbErr> {
+        let result = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(to_status))
+            .col_expr(Column::ResolvedAt, Expr::value(chrono::Utc::now().naive_utc()))
+            .filter(Column::Id.eq(id))
+            .filter(Column::Status.eq(from_status))
+            .exec(db).await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Earnings of `user_id` per period in `[from, to)`, oldest period first.
+    pub async fn earnings_by_period(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        granularity: ReportGranularity,
+        from: chrono::NaiveDateTime,
+        to: chrono::NaiveDateTime,
+    ) -> Result<Vec<EarningsBucket>, DbErr> {
+        let period = Expr::cust(format!("date_trunc('{}', \"created_at\")", granularity.date_trunc_unit()));
+        Entity::find()
+            .select_only()
+            .column_as(period.clone(), "period_start")
+            .column_as(Column::Reward.sum(), "reward")
+            .column_as(Column::Id.count(), "events")
+            .filter(Column::From.eq(user_id))
+            .filter(Column::Status.is_in([RewardStatus::Pending, RewardStatus::Confirmed]))
+            .filter(Column::CreatedAt.gte(from))
+            .filter(Column::CreatedAt.lt(to))
+            .group_by(period.clone())
+            .order_by_asc(period)
+            .into_model::<EarningsBucket>()
+            .all(db).await
+    }
+
+    /// The `limit` users with the most revenue or referrals from rewards created in `[from, to)`.
+    pub async fn leaderboard(
+        db: &impl ConnectionTrait,
+        metric: LeaderboardMetric,
+        from: chrono::NaiveDateTime,
+        to: chrono::NaiveDateTime,
+        limit: u64,
+    ) -> Result<Vec<AffiliateEarnings>, DbErr> {
+        let order = match metric {
+            LeaderboardMetric::Revenue => Self::confirmed_or_pending_reward(),
+            LeaderboardMetric::Referrals => Self::referral_count(),
+        };
+        Self::select_earnings()
+            .filter(Column::CreatedAt.gte(from))
+            .filter(Column::CreatedAt.lt(to))
+            .order_by_desc(order)
+            .limit(limit)
+            .into_model::<AffiliateEarnings>()
+            .all(db).await
+    }
+
+    /// Earnings of `user_id` from rewards created in `[from, to)`, `None` if there are none.
+    pub async fn earnings_of_user(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        from: chrono::NaiveDateTime,
+        to: chrono::NaiveDateTime,
+    ) -> Result<Option<AffiliateEarnings>, DbErr> {
+        Self::select_earnings()
+            .filter(Column::From.eq(user_id))
+            .filter(Column::CreatedAt.gte(from))
+            .filter(Column::CreatedAt.lt(to))
+            .into_model::<AffiliateEarnings>()
+            .one(db).await
+    }
+
+    fn select_earnings() -> sea_orm::Select<Entity> {
+        Entity::find()
+            .select_only()
+            .column_as(Column::From, "user_id")
+            .column_as(Self::confirmed_or_pending_reward(), "reward")
+            .column_as(Self::reward_with_status(RewardStatus::Pending), "pending_reward")
+            .column_as(Self::reward_with_status(RewardStatus::Reversed), "reversed_reward")
+            .column_as(Self::reward_with_status(RewardStatus::Held), "held_reward")
+            .column_as(Column::Id.count(), "events")
+            .column_as(Self::referral_count(), "referrals")
+            .group_by(Column::From)
+    }
+
+    fn confirmed_or_pending_reward() -> sea_orm::sea_query::SimpleExpr {
+        Expr::cust("COALESCE(SUM(\"reward\") FILTER (WHERE \"status\" IN ('pending', 'confirmed')), 0)")
+    }
+
+    fn reward_with_status(status: RewardStatus) -> sea_orm::sea_query::SimpleExpr {
+        Expr::cust_with_values("COALESCE(SUM(\"reward\") FILTER (WHERE \"status\" = $1), 0)", [status])
+    }
+
+    fn referral_count() -> sea_orm::sea_query::SimpleExpr {
+        Expr::cust("COUNT(DISTINCT \"to\") FILTER (WHERE \"level\" = 1 AND \"status\" IN ('pending', 'confirmed'))")
+    }
+
+    /// The user who directly invited `user_id`, taken from their earliest level 1 edge
+    /// which wasn't reversed or held.
+    pub async fn find_inviter(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+    ) -> Result<Option<Uuid>, DbErr> {
+        let edge = Entity::find()
+            .filter(Column::To.eq(user_id))
+            .filter(Column::Level.eq(1))
+            .filter(Column::Status.is_in([RewardStatus::Pending, RewardStatus::Confirmed]))
+            .order_by_asc(Column::Id)
+            .one(db).await?;
+        Ok(edge.map(|edge| edge.from))
+    }
+
     pub async fn find_by_id(
         db: &impl ConnectionTrait,
         id: i32,
@@ -0,0 +1,92 @@
+use sea_orm::{
+    prelude::Expr, sea_query::StringLen, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
+    ConnectionTrait, DbErr, DeriveActiveEnum, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait,
+    EnumIter, PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+pub enum LedgerEntryKind {
+    /// Money sent to the user for a paid withdrawal.
+    #[sea_orm(string_value = "payout")]
+    Payout,
+}
+
+/// Append-only record of money movements. Statistics like `withdrawn` are derived from it.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_affiliate__ledger")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(indexed)]
+    pub user_id: Uuid,
+    pub kind: LedgerEntryKind,
+    pub amount: f32,
+    /// The withdrawal a payout belongs to.
+    pub withdrawal_id: Option<i32>,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type AffiliateLedgerData = Model;
+pub type AffiliateLedgerEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AffiliateLedgerDataBeforeCreate {
+    pub user_id: Uuid,
+    pub kind: LedgerEntryKind,
+    pub amount: f32,
+    pub withdrawal_id: Option<i32>,
+}
+
+impl AffiliateLedgerData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: AffiliateLedgerDataBeforeCreate,
+    ) -> Result<Self, DbErr> {
+        ActiveModel {
+            user_id: Set(data.user_id),
+            kind: Set(data.kind),
+            amount: Set(data.amount),
+            withdrawal_id: Set(data.withdrawal_id),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            ..Default::default()
+        }.insert(db).await
+    }
+
+    pub async fn find_by_user_id(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_desc(Column::Id)
+            .offset(offset)
+            .limit(limit)
+            .all(db).await
+    }
+
+    pub async fn sum_by_kind(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        kind: LedgerEntryKind,
+    ) -> Result<f32, DbErr> {
+        let sum = Entity::find()
+            .select_only()
+            .column_as(Column::Amount.sum(), "sum")
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::Kind.eq(kind))
+            .into_tuple::<Option<f32>>()
+            .one(db).await?;
+        Ok(sum.flatten().unwrap_or(0.0))
+    }
+}
@@ -0,0 +1,85 @@
+use sea_orm::{
+    prelude::Expr, sea_query::OnConflict, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
+    ConnectionTrait, DbErr, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter,
+    PrimaryKeyTrait, QueryFilter, QueryOrder,
+};
+use uuid::Uuid;
+
+/// A code users share to be credited for the users signing up through it.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_affiliate__referral_code")]
+pub struct Model {
+    /// Stored lowercase, so codes are matched case-insensitively.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub code: String,
+    #[sea_orm(indexed)]
+    pub user_id: Uuid,
+    /// Lets one user track several campaigns separately.
+    pub campaign: Option<String>,
+    #[sea_orm(default_value = true)]
+    pub is_active: bool,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type AffiliateReferralCodeData = Model;
+pub type AffiliateReferralCodeEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AffiliateReferralCodeDataBeforeCreate {
+    pub code: String,
+    pub user_id: Uuid,
+    pub campaign: Option<String>,
+}
+
+impl AffiliateReferralCodeData {
+    /// Insert the code unless it's taken. Returns whether a row was inserted.
+    pub async fn create_if_free(
+        db: &impl ConnectionTrait,
+        data: AffiliateReferralCodeDataBeforeCreate,
+    ) -> Result<bool, DbErr> {
+        let result = Entity::insert(ActiveModel {
+            code: Set(data.code),
+            user_id: Set(data.user_id),
+            campaign: Set(data.campaign),
+            is_active: Set(true),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+        })
+            .on_conflict(OnConflict::column(Column::Code).do_nothing().to_owned())
+            .exec_without_returning(db).await?;
+        Ok(result > 0)
+    }
+
+    pub async fn find_by_code(
+        db: &impl ConnectionTrait,
+        code: &str,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find_by_id(code.to_owned()).one(db).await
+    }
+
+    pub async fn find_by_user_id(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+    ) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_asc(Column::CreatedAt)
+            .all(db).await
+    }
+
+    /// Deactivated codes are kept so past attributions still point somewhere.
+    pub async fn update_is_active(
+        db: &impl ConnectionTrait,
+        before: &AffiliateReferralCodeData,
+        is_active: bool,
+    ) -> Result<Self, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.is_active = Set(is_active);
+        active.update(db).await
+    }
+}
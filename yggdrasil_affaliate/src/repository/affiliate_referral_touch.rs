@@ -0,0 +1,111 @@
+use sea_orm::{
+    prelude::Expr, sea_query::StringLen, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
+    ConnectionTrait, DbErr, DeleteResult, DeriveActiveEnum, DeriveEntityModel, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+pub enum ReferralTouchKind {
+    /// A visitor opened a referral link.
+    #[sea_orm(string_value = "click")]
+    Click,
+    /// A code was entered during registration.
+    #[sea_orm(string_value = "signup")]
+    Signup,
+}
+
+/// A visitor coming across a referral code, before it's known which user they'll become.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_affiliate__referral_touch")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    /// Identifies the visitor across requests, e.g. a cookie or device id.
+    #[sea_orm(indexed)]
+    pub visitor_key: String,
+    pub code: String,
+    /// Owner of `code` at the time of the touch.
+    pub user_id: Uuid,
+    pub kind: ReferralTouchKind,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub touched_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type AffiliateReferralTouchData = Model;
+pub type AffiliateReferralTouchEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AffiliateReferralTouchDataBeforeCreate {
+    pub visitor_key: String,
+    pub code: String,
+    pub user_id: Uuid,
+    pub kind: ReferralTouchKind,
+}
+
+impl AffiliateReferralTouchData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: AffiliateReferralTouchDataBeforeCreate,
+    ) -> Result<Self, DbErr> {
+        ActiveModel {
+            visitor_key: Set(data.visitor_key),
+            code: Set(data.code),
+            user_id: Set(data.user_id),
+            kind: Set(data.kind),
+            touched_at: Set(chrono::Utc::now().naive_utc()),
+            ..Default::default()
+        }.insert(db).await
+    }
+
+    /// Touches of the visitor at or after `since`, oldest first.
+    pub async fn find_by_visitor_since(
+        db: &impl ConnectionTrait,
+        visitor_key: &str,
+        since: chrono::NaiveDateTime,
+    ) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::VisitorKey.eq(visitor_key))
+            .filter(Column::TouchedAt.gte(since))
+            .order_by_asc(Column::TouchedAt)
+            .order_by_asc(Column::Id)
+            .all(db).await
+    }
+
+    /// Clicks on each of `user_id`'s codes made in `[from, to)`.
+    pub async fn count_clicks_by_code(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        from: chrono::NaiveDateTime,
+        to: chrono::NaiveDateTime,
+    ) -> Result<Vec<(String, i64)>, DbErr> {
+        Entity::find()
+            .select_only()
+            .column(Column::Code)
+            .column_as(Column::Id.count(), "clicks")
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::Kind.eq(ReferralTouchKind::Click))
+            .filter(Column::TouchedAt.gte(from))
+            .filter(Column::TouchedAt.lt(to))
+            .group_by(Column::Code)
+            .into_tuple()
+            .all(db).await
+    }
+
+    /// Remove touches made before `before`, which can't be attributed anymore.
+    pub async fn delete_stale(
+        db: &impl ConnectionTrait,
+        before: chrono::NaiveDateTime,
+    ) -> Result<DeleteResult, DbErr> {
+        Entity::delete_many()
+            .filter(Column::TouchedAt.lt(before))
+            .exec(db).await
+    }
+}
@@ -1,6 +1,8 @@
+use crate::repository::{AffiliateLedgerData, LedgerEntryKind, RewardStatus};
 use sea_orm::{
-    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ConnectionTrait, DbErr,
-    DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait,
+    prelude::Expr, sea_query::OnConflict, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
+    ConnectionTrait, DbErr, DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait,
+    EnumIter, PrimaryKeyTrait, QueryFilter, QuerySelect,
 };
 use uuid::Uuid;
 
@@ -9,8 +11,12 @@ use uuid::Uuid;
 pub struct Model {
     #[sea_orm(primary_key)]
     pub user_id: Uuid,
+    /// Confirmed rewards.
     #[sea_orm(default_value = 0.0)]
     pub total: f32,
+    /// Rewards still in their holding period.
+    #[sea_orm(default_value = 0.0)]
+    pub pending_total: f32,
     #[sea_orm(default_value = 0.0)]
     pub withdrawn: f32,
     #[sea_orm(default_value = 0)]
@@ -28,8 +34,8 @@ pub type AffiliateStatisticsEntity = Entity;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct AffiliateStatisticsDataBeforeCreate {
-    user_id: Uuid,
-    rate: f32,
+    pub user_id: Uuid,
+    pub rate: f32,
 }
 
 impl AffiliateStatisticsData {
@@ -44,6 +50,24 @@ impl AffiliateStatisticsData {
         }.insert(db).await
     }
 
+    /// Create the record unless the user already has one.
+    pub async fn create_if_missing(
+        db: &impl ConnectionTrait,
+        data: AffiliateStatisticsDataBeforeCreate,
+    ) -> Result<(), DbErr> {
+        Entity::insert(ActiveModel {
+            user_id: Set(data.user_id),
+            total: Set(0.0),
+            pending_total: Set(0.0),
+            withdrawn: Set(0.0),
+            count_referrals: Set(0),
+            rate: Set(data.rate),
+        })
+            .on_conflict(OnConflict::column(Column::UserId).do_nothing().to_owned())
+            .exec_without_returning(db).await?;
+        Ok(())
+    }
+
     pub async fn update_total(
         db: &impl ConnectionTrait,
         before: &AffiliateStatisticsData,
@@ -54,14 +78,17 @@ impl AffiliateStatisticsData {
         active.update(db).await
     }
 
-    pub async fn update_withdrawn(
+    /// Set `withdrawn` to the sum of the user's payouts in the ledger.
+    pub async fn sync_withdrawn(
         db: &impl ConnectionTrait,
-        before: &AffiliateStatisticsData,
-        new_withdrawn: f32,
-    ) -> Result<AffiliateStatisticsData, DbErr> {
-        let mut active: ActiveModel = before.clone().into();
-        active.withdrawn = Set(new_withdrawn);
-        active.update(db).await
+        user_id: Uuid,
+    ) -> Result<(), DbErr> {
+        let withdrawn = AffiliateLedgerData::sum_by_kind(db, user_id, LedgerEntryKind::Payout).await?;
+        Entity::update_many()
+            .col_expr(Column::Withdrawn, Expr::value(withdrawn))
+            .filter(Column::UserId.eq(user_id))
+            .exec(db).await?;
+        Ok(())
     }
 
     pub async fn update_count_referrals(
@@ -84,18 +111,85 @@ impl AffiliateStatisticsData {
         active.update(db).await
     }
 
+    /// Add `reward` to the total matching `status` and count one more referral, incrementing
+    /// in SQL so concurrent events don't overwrite each other.
+    /// Held and reversed rewards aren't counted.
     pub async fn on_invite(
         db: &impl ConnectionTrait,
-        before: &AffiliateStatisticsData,
-        raw_amount: f32,
-    ) -> Result<AffiliateStatisticsData, DbErr> {
-        let rate = before.rate;
-        let before_total = before.total;
-        let before_count = before.count_referrals;
-        let mut active: ActiveModel = before.clone().into();
-        active.total = Set(before_total + rate * raw_amount);
-        active.count_referrals = Set(before_count + 1);
-        active.update(db).await
+        user_id: Uuid,
+        reward: f32,
+        status: RewardStatus,
+    ) -> Result<(), DbErr> {
+        let Some(column) = Self::reward_column(status) else {
+            return Ok(());
+        };
+        Entity::update_many()
+            .col_expr(column, Expr::col(column).add(reward))
+            .col_expr(Column::CountReferrals, Expr::col(Column::CountReferrals).add(1))
+            .filter(Column::UserId.eq(user_id))
+            .exec(db).await?;
+        Ok(())
+    }
+
+    /// Add `reward` to the total matching `status` without counting a referral, for rewards
+    /// from indirect referrals.
+    pub async fn add_reward(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        reward: f32,
+        status: RewardStatus,
+    ) -> Result<(), DbErr> {
+        let Some(column) = Self::reward_column(status) else {
+            return Ok(());
+        };
+        Entity::update_many()
+            .col_expr(column, Expr::col(column).add(reward))
+            .filter(Column::UserId.eq(user_id))
+            .exec(db).await?;
+        Ok(())
+    }
+
+    /// Move `reward` out of the holding period into the total.
+    pub async fn confirm_reward(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        reward: f32,
+    ) -> Result<(), DbErr> {
+        Entity::update_many()
+            .col_expr(Column::PendingTotal, Expr::col(Column::PendingTotal).sub(reward))
+            .col_expr(Column::Total, Expr::col(Column::Total).add(reward))
+            .filter(Column::UserId.eq(user_id))
+            .exec(db).await?;
+        Ok(())
+    }
+
+    /// Take back a reward previously added with `status`, uncounting the referral when
+    /// `is_referral` is set.
+    pub async fn reverse_reward(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        reward: f32,
+        status: RewardStatus,
+        is_referral: bool,
+    ) -> Result<(), DbErr> {
+        let Some(column) = Self::reward_column(status) else {
+            return Ok(());
+        };
+        let mut update = Entity::update_many()
+            .col_expr(column, Expr::col(column).sub(reward));
+        if is_referral {
+            update = update.col_expr(Column::CountReferrals, Expr::col(Column::CountReferrals).sub(1));
+        }
+        update.filter(Column::UserId.eq(user_id)).exec(db).await?;
+        Ok(())
+    }
+
+    fn reward_column(status: RewardStatus) -> Option<Column> {
+        match status {
+            RewardStatus::Pending => Some(Column::PendingTotal),
+            RewardStatus::Confirmed => Some(Column::Total),
+            RewardStatus::Held | RewardStatus::Reversed => None,
+        }
     }
 
     pub async fn find_by_id(
@@ -105,6 +199,14 @@ impl AffiliateStatisticsData {
         Entity::find_by_id(id).one(db).await
     }
 
+    /// Like [AffiliateStatisticsData::find_by_id], but locks the row until the transaction ends.
+    pub async fn find_by_id_for_update(
+        db: &impl ConnectionTrait,
+        id: Uuid,
+    ) -> Result<Option<AffiliateStatisticsData>, DbErr> {
+        Entity::find_by_id(id).lock_exclusive().one(db).await
+    }
+
     pub async fn delete_by_id(
         db: &impl ConnectionTrait,
         id: Uuid,
@@ -0,0 +1,144 @@
+use crate::repository::AffiliateGraphData;
+use sea_orm::{ConnectionTrait, DbBackend, DbErr, FromQueryResult, Statement};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Direct referrals, taking a user's earliest pending or confirmed level 1 edge as their
+/// inviter like [AffiliateGraphData::find_inviter] does, so every user has at most one parent.
+const REFERRALS_CTE: &str = r#"referrals AS (
+    SELECT DISTINCT ON ("to") "to" AS child, "from" AS parent
+    FROM ygg_affiliate__graph
+    WHERE level = 1 AND status IN ('pending', 'confirmed')
+    ORDER BY "to", id
+)"#;
+
+/// Users below `root` up to `max_depth`, walking paths that don't revisit a user so
+/// cycles in the graph can't make the query run forever.
+const SUBTREE_CTE: &str = r#"subtree(user_id, parent_id, depth, path) AS (
+    SELECT child, parent, 1, ARRAY[parent, child] FROM referrals WHERE parent = $1
+    UNION ALL
+    SELECT r.child, r.parent, s.depth + 1, s.path || r.child
+    FROM referrals r JOIN subtree s ON r.parent = s.user_id
+    WHERE s.depth < $2 AND NOT r.child = ANY(s.path)
+)"#;
+
+#[derive(Debug, Clone, PartialEq, FromQueryResult)]
+struct SubtreeRow {
+    user_id: Uuid,
+    parent_id: Uuid,
+    depth: i32,
+    reward_from_node: f32,
+}
+
+/// A user in someone's downline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReferralTreeNode {
+    pub user_id: Uuid,
+    /// 1 for users the root invited directly.
+    pub depth: i32,
+    /// What the root earned from this user's events, held and reversed rewards excluded.
+    pub reward_from_node: f32,
+    /// `reward_from_node` of this user and everyone below them.
+    pub subtree_reward: f32,
+    pub children: Vec<ReferralTreeNode>,
+}
+
+/// A user in someone's upline, `depth` 1 being their direct inviter.
+#[derive(Debug, Clone, PartialEq, FromQueryResult, Serialize, Deserialize)]
+pub struct UplineEntry {
+    pub user_id: Uuid,
+    pub depth: i32,
+}
+
+impl AffiliateGraphData {
+    /// The downline of `user_id` up to `max_depth` levels, or the whole downline when `None`.
+    pub async fn find_subtree(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        max_depth: Option<i32>,
+    ) -> Result<Vec<ReferralTreeNode>, DbErr> {
+        let sql = format!(r#"WITH RECURSIVE {REFERRALS_CTE}, {SUBTREE_CTE}
+            SELECT s.user_id, s.parent_id, s.depth,
+                CAST(COALESCE(SUM(g.reward) FILTER (WHERE g.status IN ('pending', 'confirmed')), 0) AS REAL) AS reward_from_node
+            FROM subtree s
+            LEFT JOIN ygg_affiliate__graph g ON g."from" = $1 AND g."to" = s.user_id
+            GROUP BY s.user_id, s.parent_id, s.depth
+            ORDER BY s.depth"#);
+        let rows = SubtreeRow::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            sql,
+            [user_id.into(), max_depth.unwrap_or(i32::MAX).into()],
+        )).all(db).await?;
+
+        let mut children_of: HashMap<Uuid, Vec<SubtreeRow>> = HashMap::new();
+        for row in rows {
+            children_of.entry(row.parent_id).or_default().push(row);
+        }
+        Ok(Self::build_nodes(user_id, &mut children_of))
+    }
+
+    fn build_nodes(parent_id: Uuid, children_of: &mut HashMap<Uuid, Vec<SubtreeRow>>) -> Vec<ReferralTreeNode> {
+        let rows = children_of.remove(&parent_id).unwrap_or_default();
+        rows.into_iter().map(|row| {
+            let children = Self::build_nodes(row.user_id, children_of);
+            let subtree_reward = row.reward_from_node + children.iter().map(|child| child.subtree_reward).sum::<f32>();
+            ReferralTreeNode {
+                user_id: row.user_id,
+                depth: row.depth,
+                reward_from_node: row.reward_from_node,
+                subtree_reward,
+                children,
+            }
+        }).collect()
+    }
+
+    /// Users invited directly by `user_id`, without their own referrals.
+    pub async fn find_children(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+    ) -> Result<Vec<ReferralTreeNode>, DbErr> {
+        Self::find_subtree(db, user_id, Some(1)).await
+    }
+
+    /// Number of users in the downline of `user_id` up to `max_depth` levels.
+    pub async fn count_subtree(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        max_depth: Option<i32>,
+    ) -> Result<i64, DbErr> {
+        let sql = format!(r#"WITH RECURSIVE {REFERRALS_CTE}, {SUBTREE_CTE}
+            SELECT COUNT(DISTINCT user_id) AS count FROM subtree"#);
+        let row = db.query_one(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            sql,
+            [user_id.into(), max_depth.unwrap_or(i32::MAX).into()],
+        )).await?;
+        match row {
+            Some(row) => row.try_get("", "count"),
+            None => Ok(0),
+        }
+    }
+
+    /// The chain of inviters above `user_id`, nearest first, up to `max_depth` levels.
+    pub async fn find_upline(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        max_depth: Option<i32>,
+    ) -> Result<Vec<UplineEntry>, DbErr> {
+        let sql = format!(r#"WITH RECURSIVE {REFERRALS_CTE},
+            upline(user_id, depth, path) AS (
+                SELECT parent, 1, ARRAY[child, parent] FROM referrals WHERE child = $1
+                UNION ALL
+                SELECT r.parent, u.depth + 1, u.path || r.parent
+                FROM referrals r JOIN upline u ON r.child = u.user_id
+                WHERE u.depth < $2 AND NOT r.parent = ANY(u.path)
+            )
+            SELECT user_id, depth FROM upline ORDER BY depth"#);
+        UplineEntry::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            sql,
+            [user_id.into(), max_depth.unwrap_or(i32::MAX).into()],
+        )).all(db).await
+    }
+}
@@ -0,0 +1,162 @@
+use sea_orm::{
+    prelude::Expr, sea_query::StringLen, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
+    ConnectionTrait, DbErr, DeriveActiveEnum, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait,
+    EnumIter, Iterable, PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+pub enum WithdrawalStatus {
+    #[sea_orm(string_value = "requested")]
+    Requested,
+    #[sea_orm(string_value = "approved")]
+    Approved,
+    #[sea_orm(string_value = "paid")]
+    Paid,
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
+}
+
+impl WithdrawalStatus {
+    /// Whether the amount is still reserved from the balance, waiting to be paid out.
+    pub fn is_pending(self) -> bool {
+        matches!(self, WithdrawalStatus::Requested | WithdrawalStatus::Approved)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_affiliate__withdrawal")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(indexed)]
+    pub user_id: Uuid,
+    pub amount: f32,
+    /// How the money is sent, e.g. `paypal` or `bank_transfer`.
+    pub method: String,
+    #[sea_orm(indexed)]
+    pub status: WithdrawalStatus,
+    /// The admin who approved or rejected the request.
+    pub reviewed_by: Option<Uuid>,
+    pub note: Option<String>,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created_at: chrono::NaiveDateTime,
+    pub reviewed_at: Option<chrono::NaiveDateTime>,
+    pub paid_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type AffiliateWithdrawalData = Model;
+pub type AffiliateWithdrawalEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AffiliateWithdrawalDataBeforeCreate {
+    pub user_id: Uuid,
+    pub amount: f32,
+    pub method: String,
+}
+
+impl AffiliateWithdrawalData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: AffiliateWithdrawalDataBeforeCreate,
+    ) -> Result<Self, DbErr> {
+        ActiveModel {
+            user_id: Set(data.user_id),
+            amount: Set(data.amount),
+            method: Set(data.method),
+            status: Set(WithdrawalStatus::Requested),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            ..Default::default()
+        }.insert(db).await
+    }
+
+    pub async fn find_by_id(
+        db: &impl ConnectionTrait,
+        id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find_by_id(id).one(db).await
+    }
+
+    /// Like [AffiliateWithdrawalData::find_by_id], but locks the row until the transaction ends.
+    pub async fn find_by_id_for_update(
+        db: &impl ConnectionTrait,
+        id: i32,
+    ) -> Result<Option<Self>, DbErr> {
+        Entity::find_by_id(id).lock_exclusive().one(db).await
+    }
+
+    pub async fn find_by_user_id(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_desc(Column::CreatedAt)
+            .offset(offset)
+            .limit(limit)
+            .all(db).await
+    }
+
+    pub async fn find_by_status(
+        db: &impl ConnectionTrait,
+        status: WithdrawalStatus,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<Self>, DbErr> {
+        Entity::find()
+            .filter(Column::Status.eq(status))
+            .order_by_asc(Column::CreatedAt)
+            .offset(offset)
+            .limit(limit)
+            .all(db).await
+    }
+
+    /// Sum of the withdrawals still reserved from the balance, see [WithdrawalStatus::is_pending].
+    pub async fn pending_amount(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+    ) -> Result<f32, DbErr> {
+        let sum = Entity::find()
+            .select_only()
+            .column_as(Column::Amount.sum(), "sum")
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::Status.is_in(WithdrawalStatus::iter().filter(|status| status.is_pending())))
+            .into_tuple::<Option<f32>>()
+            .one(db).await?;
+        Ok(sum.flatten().unwrap_or(0.0))
+    }
+
+    pub async fn review(
+        db: &impl ConnectionTrait,
+        before: &AffiliateWithdrawalData,
+        status: WithdrawalStatus,
+        reviewed_by: Uuid,
+        note: Option<String>,
+    ) -> Result<Self, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.status = Set(status);
+        active.reviewed_by = Set(Some(reviewed_by));
+        active.note = Set(note);
+        active.reviewed_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active.update(db).await
+    }
+
+    pub async fn mark_paid(
+        db: &impl ConnectionTrait,
+        before: &AffiliateWithdrawalData,
+    ) -> Result<Self, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.status = Set(WithdrawalStatus::Paid);
+        active.paid_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active.update(db).await
+    }
+}
@@ -1,10 +1,47 @@
+mod affiliate_attribution;
 mod affiliate_graph;
+mod affiliate_ledger;
+mod affiliate_referral_code;
+mod affiliate_referral_touch;
 mod affiliate_statistics;
+mod affiliate_tree;
+mod affiliate_withdrawal;
+
+pub use affiliate_attribution::{
+    AffiliateAttributionData,
+    AffiliateAttributionDataBeforeCreate,
+    AffiliateAttributionEntity,
+};
 
 pub use affiliate_graph::{
+    AffiliateEarnings,
     AffiliateGraphData,
     AffiliateGraphDataBeforeCreate,
     AffiliateGraphEntity,
+    EarningsBucket,
+    LeaderboardMetric,
+    ReportGranularity,
+    RewardStatus,
+};
+
+pub use affiliate_ledger::{
+    AffiliateLedgerData,
+    AffiliateLedgerDataBeforeCreate,
+    AffiliateLedgerEntity,
+    LedgerEntryKind,
+};
+
+pub use affiliate_referral_code::{
+    AffiliateReferralCodeData,
+    AffiliateReferralCodeDataBeforeCreate,
+    AffiliateReferralCodeEntity,
+};
+
+pub use affiliate_referral_touch::{
+    AffiliateReferralTouchData,
+    AffiliateReferralTouchDataBeforeCreate,
+    AffiliateReferralTouchEntity,
+    ReferralTouchKind,
 };
 
 pub use affiliate_statistics::{
@@ -12,3 +49,15 @@ pub use affiliate_statistics::{
     AffiliateStatisticsDataBeforeCreate,
     AffiliateStatisticsEntity,
 };
+
+pub use affiliate_tree::{
+    ReferralTreeNode,
+    UplineEntry,
+};
+
+pub use affiliate_withdrawal::{
+    AffiliateWithdrawalData,
+    AffiliateWithdrawalDataBeforeCreate,
+    AffiliateWithdrawalEntity,
+    WithdrawalStatus,
+};
\ No newline at end of file
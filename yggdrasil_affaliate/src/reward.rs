@@ -0,0 +1,125 @@
+use crate::error::AffiliateError;
+use crate::repository::{AffiliateGraphData, AffiliateStatisticsData, RewardStatus};
+use sea_orm::{ConnectionTrait, DatabaseTransaction, TransactionTrait};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Confirm up to `limit` pending rewards whose holding period is over, making them withdrawable.
+/// Meant to run periodically, returns how many were confirmed.
+pub async fn confirm_matured_rewards(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    limit: u64,
+) -> Result<u64, AffiliateError> {
+    let now = chrono::Utc::now().naive_utc();
+    let due = AffiliateGraphData::find_pending_due(db, now, limit).await?;
+    let mut confirmed = 0;
+    for edge in due {
+        let applied = db.transaction::<_, bool, AffiliateError>(|tx| {
+            Box::pin(async move {
+                if !AffiliateGraphData::transition_status(tx, edge.id, RewardStatus::Pending, RewardStatus::Confirmed).await? {
+                    return Ok(false);
+                }
+                AffiliateStatisticsData::confirm_reward(tx, edge.from, edge.reward).await?;
+                Ok(true)
+            })
+        }).await.map_err(AffiliateError::from)?;
+        if applied {
+            confirmed += 1;
+        }
+    }
+    Ok(confirmed)
+}
+
+/// Reverse every reward paid for `order_reference`, e.g. when the order is refunded.
+/// Rewards already reversed are skipped, returns how many were reversed by this call.
+///
+/// Confirmed rewards are taken out of `total` even when already withdrawn, leaving the
+/// inviter's balance negative until new rewards make up for it.
+pub async fn reverse_rewards_for_order(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    order_reference: &str,
+) -> Result<u64, AffiliateError> {
+    let order_reference = order_reference.to_owned();
+    db.transaction::<_, u64, AffiliateError>(|tx| {
+        Box::pin(async move {
+            let edges = AffiliateGraphData::find_by_order_reference(tx, &order_reference).await?;
+            if edges.is_empty() {
+                return Err(AffiliateError::OrderNotFound(order_reference));
+            }
+            let mut reversed = 0;
+            for edge in edges {
+                if edge.status == RewardStatus::Reversed {
+                    continue;
+                }
+                // Held rewards were never counted, nothing to take back from the statistics.
+                if !AffiliateGraphData::transition_status(tx, edge.id, edge.status, RewardStatus::Reversed).await? {
+                    warn!("Yggdrasil Affiliate Module: Reward ({}) of order ({}) changed status while being reversed.",
+                        edge.id, order_reference);
+                    continue;
+                }
+                AffiliateStatisticsData::reverse_reward(tx, edge.from, edge.reward, edge.status, edge.level == 1).await?;
+                reversed += 1;
+            }
+            Ok(reversed)
+        })
+    }).await.map_err(AffiliateError::from)
+}
+
+/// Count the held rewards of an event as if it had passed the fraud checks, pending until
+/// their `confirm_after` if that's still ahead.
+pub async fn release_held_event(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    event_id: Uuid,
+) -> Result<u64, AffiliateError> {
+    db.transaction::<_, u64, AffiliateError>(|tx| {
+        Box::pin(async move {
+            let now = chrono::Utc::now().naive_utc();
+            let mut released = 0;
+            for edge in held_edges(tx, event_id).await? {
+                let status = if edge.confirm_after > now { RewardStatus::Pending } else { RewardStatus::Confirmed };
+                if !AffiliateGraphData::transition_status(tx, edge.id, RewardStatus::Held, status).await? {
+                    continue;
+                }
+                if edge.level == 1 {
+                    AffiliateStatisticsData::on_invite(tx, edge.from, edge.reward, status).await?;
+                } else {
+                    AffiliateStatisticsData::add_reward(tx, edge.from, edge.reward, status).await?;
+                }
+                released += 1;
+            }
+            Ok(released)
+        })
+    }).await.map_err(AffiliateError::from)
+}
+
+/// Reverse the held rewards of an event found to be fraudulent.
+pub async fn reject_held_event(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    event_id: Uuid,
+) -> Result<u64, AffiliateError> {
+    db.transaction::<_, u64, AffiliateError>(|tx| {
+        Box::pin(async move {
+            let mut rejected = 0;
+            for edge in held_edges(tx, event_id).await? {
+                if AffiliateGraphData::transition_status(tx, edge.id, RewardStatus::Held, RewardStatus::Reversed).await? {
+                    rejected += 1;
+                }
+            }
+            Ok(rejected)
+        })
+    }).await.map_err(AffiliateError::from)
+}
+
+async fn held_edges(
+    tx: &DatabaseTransaction,
+    event_id: Uuid,
+) -> Result<Vec<AffiliateGraphData>, AffiliateError> {
+    let edges: Vec<_> = AffiliateGraphData::find_by_event_id(tx, event_id).await?
+        .into_iter()
+        .filter(|edge| edge.status == RewardStatus::Held)
+        .collect();
+    if edges.is_empty() {
+        return Err(AffiliateError::EventNotHeld(event_id));
+    }
+    Ok(edges)
+}
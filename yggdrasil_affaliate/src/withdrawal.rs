@@ -0,0 +1,133 @@
+use crate::error::AffiliateError;
+use crate::event_handler::AffiliateSettings;
+use crate::repository::{
+    AffiliateLedgerData, AffiliateLedgerDataBeforeCreate, AffiliateStatisticsData, AffiliateWithdrawalData,
+    AffiliateWithdrawalDataBeforeCreate, LedgerEntryKind, WithdrawalStatus,
+};
+use sea_orm::{ConnectionTrait, DatabaseTransaction, TransactionTrait};
+use std::future::Future;
+use std::pin::Pin;
+use uuid::Uuid;
+
+/// What the user can still request to withdraw: `total - withdrawn - pending`.
+pub async fn available_balance(
+    db: &impl ConnectionTrait,
+    user_id: Uuid,
+) -> Result<f32, AffiliateError> {
+    let statistics = AffiliateStatisticsData::find_by_id(db, user_id).await?
+        .ok_or(AffiliateError::UserNotFound(user_id))?;
+    let pending = AffiliateWithdrawalData::pending_amount(db, user_id).await?;
+    Ok(statistics.total - statistics.withdrawn - pending)
+}
+
+/// Reserve `amount` of the user's balance for an admin to review.
+pub async fn request_withdrawal(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    user_id: Uuid,
+    amount: f32,
+    method: &str,
+    settings: &AffiliateSettings,
+) -> Result<AffiliateWithdrawalData, AffiliateError> {
+    if amount.is_nan() || amount <= 0.0 {
+        return Err(AffiliateError::InvalidAmount(amount));
+    }
+    if amount < settings.minimum_payout {
+        return Err(AffiliateError::BelowMinimumPayout {
+            amount,
+            minimum: settings.minimum_payout,
+        });
+    }
+    let method = method.to_owned();
+    in_transaction(db, move |tx| Box::pin(async move {
+        // Serializes concurrent requests of the same user, so they can't overdraw together.
+        let statistics = AffiliateStatisticsData::find_by_id_for_update(tx, user_id).await?
+            .ok_or(AffiliateError::UserNotFound(user_id))?;
+        let pending = AffiliateWithdrawalData::pending_amount(tx, user_id).await?;
+        let available = statistics.total - statistics.withdrawn - pending;
+        if amount > available {
+            return Err(AffiliateError::InsufficientBalance {
+                requested: amount,
+                available,
+            });
+        }
+        Ok(AffiliateWithdrawalData::create(tx, AffiliateWithdrawalDataBeforeCreate {
+            user_id,
+            amount,
+            method,
+        }).await?)
+    })).await
+}
+
+pub async fn approve_withdrawal(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    withdrawal_id: i32,
+    admin_id: Uuid,
+) -> Result<AffiliateWithdrawalData, AffiliateError> {
+    in_transaction(db, move |tx| Box::pin(async move {
+        let withdrawal = find_with_status(tx, withdrawal_id, &[WithdrawalStatus::Requested]).await?;
+        Ok(AffiliateWithdrawalData::review(tx, &withdrawal, WithdrawalStatus::Approved, admin_id, None).await?)
+    })).await
+}
+
+/// Reject a withdrawal not paid yet, releasing its amount back to the balance.
+pub async fn reject_withdrawal(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    withdrawal_id: i32,
+    admin_id: Uuid,
+    reason: Option<String>,
+) -> Result<AffiliateWithdrawalData, AffiliateError> {
+    in_transaction(db, move |tx| Box::pin(async move {
+        let withdrawal = find_with_status(
+            tx, withdrawal_id, &[WithdrawalStatus::Requested, WithdrawalStatus::Approved],
+        ).await?;
+        Ok(AffiliateWithdrawalData::review(tx, &withdrawal, WithdrawalStatus::Rejected, admin_id, reason).await?)
+    })).await
+}
+
+/// Record that an approved withdrawal was paid out, adding it to the ledger and `withdrawn`.
+pub async fn mark_withdrawal_paid(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    withdrawal_id: i32,
+) -> Result<AffiliateWithdrawalData, AffiliateError> {
+    in_transaction(db, move |tx| Box::pin(async move {
+        let withdrawal = find_with_status(tx, withdrawal_id, &[WithdrawalStatus::Approved]).await?;
+        AffiliateStatisticsData::find_by_id_for_update(tx, withdrawal.user_id).await?;
+        let withdrawal = AffiliateWithdrawalData::mark_paid(tx, &withdrawal).await?;
+        AffiliateLedgerData::create(tx, AffiliateLedgerDataBeforeCreate {
+            user_id: withdrawal.user_id,
+            kind: LedgerEntryKind::Payout,
+            amount: withdrawal.amount,
+            withdrawal_id: Some(withdrawal.id),
+        }).await?;
+        AffiliateStatisticsData::sync_withdrawn(tx, withdrawal.user_id).await?;
+        Ok(withdrawal)
+    })).await
+}
+
+async fn find_with_status(
+    tx: &DatabaseTransaction,
+    withdrawal_id: i32,
+    allowed: &[WithdrawalStatus],
+) -> Result<AffiliateWithdrawalData, AffiliateError> {
+    let withdrawal = AffiliateWithdrawalData::find_by_id_for_update(tx, withdrawal_id).await?
+        .ok_or(AffiliateError::WithdrawalNotFound(withdrawal_id))?;
+    if !allowed.contains(&withdrawal.status) {
+        return Err(AffiliateError::InvalidWithdrawalStatus {
+            id: withdrawal_id,
+            status: withdrawal.status,
+        });
+    }
+    Ok(withdrawal)
+}
+
+async fn in_transaction<T, F>(
+    db: &impl TransactionTrait,
+    callback: F,
+) -> Result<T, AffiliateError>
+where
+    T: Send,
+    F: for<'c> FnOnce(&'c DatabaseTransaction) -> Pin<Box<dyn Future<Output = Result<T, AffiliateError>> + Send + 'c>>
+        + Send,
+{
+    db.transaction(callback).await.map_err(AffiliateError::from)
+}
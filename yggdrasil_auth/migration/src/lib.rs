@@ -1,12 +1,34 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20261019_000001_create_passkey_table;
+mod m20261019_000002_create_login_attempt_table;
+mod m20261019_000003_unique_user_auth_pair;
+mod m20261019_000004_create_role_tables;
+mod m20261019_000005_create_api_key_table;
+mod m20261019_000006_create_audit_log_table;
+mod m20261019_000007_create_magic_link_table;
+mod m20261019_000008_create_phone_table;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
+    fn migration_table_name() -> DynIden {
+        Alias::new("ygg_auth__seaql_migrations").into_iden()
+    }
+
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261019_000001_create_passkey_table::Migration),
+            Box::new(m20261019_000002_create_login_attempt_table::Migration),
+            Box::new(m20261019_000003_unique_user_auth_pair::Migration),
+            Box::new(m20261019_000004_create_role_tables::Migration),
+            Box::new(m20261019_000005_create_api_key_table::Migration),
+            Box::new(m20261019_000006_create_audit_log_table::Migration),
+            Box::new(m20261019_000007_create_magic_link_table::Migration),
+            Box::new(m20261019_000008_create_phone_table::Migration),
+        ]
     }
 }
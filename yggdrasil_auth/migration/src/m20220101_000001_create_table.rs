@@ -1,4 +1,4 @@
-use sea_orm_migration::{prelude::*, schema::*};
+use sea_orm_migration::prelude::*;
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -38,11 +38,12 @@ impl MigrationTrait for Migration {
                 .col(ColumnDef::new(UserAuthPair::AuthKey).string().not_null())
                 .col(ColumnDef::new(UserAuthPair::UserId).uuid().not_null())
                 .col(ColumnDef::new(UserAuthPair::IsVerified).boolean().not_null().default(false))
-                .col(ColumnDef::new(UserAuthPair::VerifiedAt).timestamp().nullable())
+                .col(ColumnDef::new(UserAuthPair::VerifiedAt).timestamp().null())
                 .to_owned()
         ).await?;
         manager.create_index(
             Index::create()
+                .if_not_exists()
                 .table(UserAuthPair::Table)
                 .name("ygg_auth__pair_provider_index")
                 .col(UserAuthPair::AuthProvider)
@@ -51,6 +52,7 @@ impl MigrationTrait for Migration {
         ).await?;
         manager.create_index(
             Index::create()
+                .if_not_exists()
                 .table(UserAuthPair::Table)
                 .name("ygg_auth__pair_user_id_index")
                 .col(UserAuthPair::UserId)
@@ -62,13 +64,14 @@ impl MigrationTrait for Migration {
                 .if_not_exists()
                 .col(ColumnDef::new(EmailProvider::Email).string().not_null().primary_key())
                 .col(ColumnDef::new(EmailProvider::PasswordHash).text().not_null())
-                .col(ColumnDef::new(EmailProvider::AuthKey).uuid().not_null().unique())
-                .col(ColumnDef::new(EmailProvider::VerifyCode).string().nullable())
-                .col(ColumnDef::new(EmailProvider::CodeSentAt).timestamp().nullable())
+                .col(ColumnDef::new(EmailProvider::AuthKey).uuid().not_null().unique_key())
+                .col(ColumnDef::new(EmailProvider::VerifyCode).string().null())
+                .col(ColumnDef::new(EmailProvider::CodeSentAt).timestamp().null())
                 .to_owned()
         ).await?;
         manager.create_index(
             Index::create()
+                .if_not_exists()
                 .table(EmailProvider::Table)
                 .name("ygg_auth__email_provider_auth_key_index")
                 .col(EmailProvider::AuthKey)
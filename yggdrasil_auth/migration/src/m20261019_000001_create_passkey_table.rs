@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum PasskeyCredential {
+    #[sea_orm(iden = "ygg_auth__passkey_credential")]
+    Table,
+    CredentialId,
+    PublicKey,
+    AuthKey,
+    SignCount,
+    CreatedAt,
+    LastUsedAt,
+}
+
+#[derive(DeriveIden)]
+enum PasskeyChallenge {
+    #[sea_orm(iden = "ygg_auth__passkey_challenge")]
+    Table,
+    Challenge,
+    Ceremony,
+    UserId,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(PasskeyCredential::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(PasskeyCredential::CredentialId).string().not_null().primary_key())
+                .col(ColumnDef::new(PasskeyCredential::PublicKey).binary().not_null())
+                .col(ColumnDef::new(PasskeyCredential::AuthKey).uuid().not_null().unique_key())
+                .col(ColumnDef::new(PasskeyCredential::SignCount).big_integer().not_null().default(0))
+                .col(ColumnDef::new(PasskeyCredential::CreatedAt).timestamp().not_null())
+                .col(ColumnDef::new(PasskeyCredential::LastUsedAt).timestamp().null())
+                .to_owned()
+        ).await?;
+        manager.create_table(
+            Table::create()
+                .table(PasskeyChallenge::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(PasskeyChallenge::Challenge).string().not_null().primary_key())
+                .col(ColumnDef::new(PasskeyChallenge::Ceremony).string().not_null())
+                .col(ColumnDef::new(PasskeyChallenge::UserId).uuid().null())
+                .col(ColumnDef::new(PasskeyChallenge::CreatedAt).timestamp().not_null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(PasskeyChallenge::Table)
+                .name("ygg_auth__passkey_challenge_created_at_index")
+                .col(PasskeyChallenge::CreatedAt)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__passkey_challenge_created_at_index")
+                .table(PasskeyChallenge::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(PasskeyChallenge::Table).to_owned()).await?;
+        manager.drop_table(Table::drop().table(PasskeyCredential::Table).to_owned()).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum LoginAttempt {
+    #[sea_orm(iden = "ygg_auth__login_attempt")]
+    Table,
+    ThrottleKey,
+    Failures,
+    WindowStartedAt,
+    LastFailureAt,
+    LockedUntil,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(LoginAttempt::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(LoginAttempt::ThrottleKey).string().not_null().primary_key())
+                .col(ColumnDef::new(LoginAttempt::Failures).integer().not_null())
+                .col(ColumnDef::new(LoginAttempt::WindowStartedAt).timestamp().not_null())
+                .col(ColumnDef::new(LoginAttempt::LastFailureAt).timestamp().not_null())
+                .col(ColumnDef::new(LoginAttempt::LockedUntil).timestamp().null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(LoginAttempt::Table)
+                .name("ygg_auth__login_attempt_last_failure_at_index")
+                .col(LoginAttempt::LastFailureAt)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__login_attempt_last_failure_at_index")
+                .table(LoginAttempt::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(LoginAttempt::Table).to_owned()).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum UserAuthPair {
+    #[sea_orm(iden = "ygg_auth__user_auth_pair")]
+    Table,
+    AuthProvider,
+    AuthKey,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__pair_provider_index")
+                .table(UserAuthPair::Table)
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(UserAuthPair::Table)
+                .name("ygg_auth__pair_provider_unique_index")
+                .col(UserAuthPair::AuthProvider)
+                .col(UserAuthPair::AuthKey)
+                .unique()
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__pair_provider_unique_index")
+                .table(UserAuthPair::Table)
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(UserAuthPair::Table)
+                .name("ygg_auth__pair_provider_index")
+                .col(UserAuthPair::AuthProvider)
+                .col(UserAuthPair::AuthKey)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Role {
+    #[sea_orm(iden = "ygg_auth__role")]
+    Table,
+    IdNumber,
+    Name,
+    Description,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RolePermission {
+    #[sea_orm(iden = "ygg_auth__role_permission")]
+    Table,
+    IdNumber,
+    RoleId,
+    Permission,
+}
+
+#[derive(DeriveIden)]
+enum UserRole {
+    #[sea_orm(iden = "ygg_auth__user_role")]
+    Table,
+    IdNumber,
+    UserId,
+    RoleId,
+    Resource,
+    GrantedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(Role::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(Role::IdNumber).integer().not_null().auto_increment().primary_key())
+                .col(ColumnDef::new(Role::Name).string().not_null().unique_key())
+                .col(ColumnDef::new(Role::Description).string().not_null())
+                .col(ColumnDef::new(Role::CreatedAt).timestamp().not_null())
+                .to_owned()
+        ).await?;
+        manager.create_table(
+            Table::create()
+                .table(RolePermission::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(RolePermission::IdNumber).integer().not_null().auto_increment().primary_key())
+                .col(ColumnDef::new(RolePermission::RoleId).integer().not_null())
+                .col(ColumnDef::new(RolePermission::Permission).string().not_null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(RolePermission::Table)
+                .name("ygg_auth__role_permission_unique_index")
+                .col(RolePermission::RoleId)
+                .col(RolePermission::Permission)
+                .unique()
+                .to_owned()
+        ).await?;
+        manager.create_table(
+            Table::create()
+                .table(UserRole::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(UserRole::IdNumber).integer().not_null().auto_increment().primary_key())
+                .col(ColumnDef::new(UserRole::UserId).uuid().not_null())
+                .col(ColumnDef::new(UserRole::RoleId).integer().not_null())
+                .col(ColumnDef::new(UserRole::Resource).string().null())
+                .col(ColumnDef::new(UserRole::GrantedAt).timestamp().not_null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(UserRole::Table)
+                .name("ygg_auth__user_role_user_id_index")
+                .col(UserRole::UserId)
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(UserRole::Table)
+                .name("ygg_auth__user_role_role_id_index")
+                .col(UserRole::RoleId)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__user_role_role_id_index")
+                .table(UserRole::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__user_role_user_id_index")
+                .table(UserRole::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(UserRole::Table).to_owned()).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__role_permission_unique_index")
+                .table(RolePermission::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(RolePermission::Table).to_owned()).await?;
+        manager.drop_table(Table::drop().table(Role::Table).to_owned()).await?;
+        Ok(())
+    }
+}
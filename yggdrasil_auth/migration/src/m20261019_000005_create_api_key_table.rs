@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum ApiKey {
+    #[sea_orm(iden = "ygg_auth__api_key")]
+    Table,
+    KeyId,
+    KeyHash,
+    AuthKey,
+    UserId,
+    Name,
+    Scopes,
+    CreatedAt,
+    ExpiresAt,
+    LastUsedAt,
+    RevokedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(ApiKey::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(ApiKey::KeyId).string().not_null().primary_key())
+                .col(ColumnDef::new(ApiKey::KeyHash).string().not_null())
+                .col(ColumnDef::new(ApiKey::AuthKey).uuid().not_null().unique_key())
+                .col(ColumnDef::new(ApiKey::UserId).uuid().not_null())
+                .col(ColumnDef::new(ApiKey::Name).string().not_null())
+                .col(ColumnDef::new(ApiKey::Scopes).text().not_null())
+                .col(ColumnDef::new(ApiKey::CreatedAt).timestamp().not_null())
+                .col(ColumnDef::new(ApiKey::ExpiresAt).timestamp().null())
+                .col(ColumnDef::new(ApiKey::LastUsedAt).timestamp().null())
+                .col(ColumnDef::new(ApiKey::RevokedAt).timestamp().null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(ApiKey::Table)
+                .name("ygg_auth__api_key_user_id_index")
+                .col(ApiKey::UserId)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__api_key_user_id_index")
+                .table(ApiKey::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(ApiKey::Table).to_owned()).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum AuditLog {
+    #[sea_orm(iden = "ygg_auth__audit_log")]
+    Table,
+    IdNumber,
+    Kind,
+    AuthProvider,
+    UserId,
+    Subject,
+    Ip,
+    UserAgent,
+    Detail,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(AuditLog::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(AuditLog::IdNumber).big_integer().not_null().auto_increment().primary_key())
+                .col(ColumnDef::new(AuditLog::Kind).string_len(32).not_null())
+                .col(ColumnDef::new(AuditLog::AuthProvider).string().not_null())
+                .col(ColumnDef::new(AuditLog::UserId).uuid().null())
+                .col(ColumnDef::new(AuditLog::Subject).string().null())
+                .col(ColumnDef::new(AuditLog::Ip).string().null())
+                .col(ColumnDef::new(AuditLog::UserAgent).text().null())
+                .col(ColumnDef::new(AuditLog::Detail).text().null())
+                .col(ColumnDef::new(AuditLog::CreatedAt).timestamp().not_null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AuditLog::Table)
+                .name("ygg_auth__audit_log_user_id_created_at_index")
+                .col(AuditLog::UserId)
+                .col(AuditLog::CreatedAt)
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(AuditLog::Table)
+                .name("ygg_auth__audit_log_created_at_index")
+                .col(AuditLog::CreatedAt)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__audit_log_created_at_index")
+                .table(AuditLog::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__audit_log_user_id_created_at_index")
+                .table(AuditLog::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(AuditLog::Table).to_owned()).await?;
+        Ok(())
+    }
+}
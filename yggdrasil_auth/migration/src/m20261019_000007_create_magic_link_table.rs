@@ -0,0 +1,62 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum MagicLink {
+    #[sea_orm(iden = "ygg_auth__magic_link")]
+    Table,
+    TokenHash,
+    Email,
+    CreatedAt,
+    ExpiresAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(MagicLink::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(MagicLink::TokenHash).string().not_null().primary_key())
+                .col(ColumnDef::new(MagicLink::Email).string().not_null())
+                .col(ColumnDef::new(MagicLink::CreatedAt).timestamp().not_null())
+                .col(ColumnDef::new(MagicLink::ExpiresAt).timestamp().not_null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(MagicLink::Table)
+                .name("ygg_auth__magic_link_email_index")
+                .col(MagicLink::Email)
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(MagicLink::Table)
+                .name("ygg_auth__magic_link_expires_at_index")
+                .col(MagicLink::ExpiresAt)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__magic_link_expires_at_index")
+                .table(MagicLink::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_auth__magic_link_email_index")
+                .table(MagicLink::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(MagicLink::Table).to_owned()).await?;
+        Ok(())
+    }
+}
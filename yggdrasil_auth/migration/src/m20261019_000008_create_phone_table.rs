@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum PhoneProvider {
+    #[sea_orm(iden = "ygg_auth__phone_provider")]
+    Table,
+    PhoneNumber,
+    AuthKey,
+    VerifyCode,
+    CodeSentAt,
+    VerifyAttempts,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum EmailProvider {
+    #[sea_orm(iden = "ygg_auth__email_provider")]
+    Table,
+    VerifyAttempts,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(PhoneProvider::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(PhoneProvider::PhoneNumber).string().not_null().primary_key())
+                .col(ColumnDef::new(PhoneProvider::AuthKey).uuid().not_null().unique_key())
+                .col(ColumnDef::new(PhoneProvider::VerifyCode).string().null())
+                .col(ColumnDef::new(PhoneProvider::CodeSentAt).timestamp().null())
+                .col(ColumnDef::new(PhoneProvider::VerifyAttempts).integer().not_null().default(0))
+                .col(ColumnDef::new(PhoneProvider::CreatedAt).timestamp().not_null())
+                .to_owned()
+        ).await?;
+        manager.alter_table(
+            Table::alter()
+                .table(EmailProvider::Table)
+                .add_column(ColumnDef::new(EmailProvider::VerifyAttempts).integer().not_null().default(0))
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(EmailProvider::Table)
+                .drop_column(EmailProvider::VerifyAttempts)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(PhoneProvider::Table).to_owned()).await?;
+        Ok(())
+    }
+}
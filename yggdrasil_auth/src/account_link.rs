@@ -0,0 +1,124 @@
+use crate::audit_log::{AuditEntry, AuditLog};
+use crate::auth_provider::{AuthContext, AuthError, AuthProvider};
+use crate::repository::{AuditEventKind, UserAuthPairData};
+use sea_orm::{ConnectionTrait, SqlErr, TransactionTrait};
+use uuid::Uuid;
+
+/// Re-read `current` and make sure it still belongs to a verified login.
+async fn check_session(
+    db: &impl ConnectionTrait,
+    current: &UserAuthPairData,
+) -> Result<UserAuthPairData, AuthError> {
+    let maybe_pair = UserAuthPairData::find_by_key(db, &current.auth_provider, &current.auth_key)
+        .await
+        .map_err(AuthError::DatabaseError)?;
+    match maybe_pair {
+        Some(pair) if pair.user_id == current.user_id && pair.is_verified => Ok(pair),
+        _ => Err(AuthError::UnverifiedSession),
+    }
+}
+
+/// Every login method of `user_id`.
+pub async fn list_linked(
+    db: &impl ConnectionTrait,
+    user_id: Uuid,
+) -> Result<Vec<UserAuthPairData>, AuthError> {
+    UserAuthPairData::find_by_user_id(db, user_id)
+        .await
+        .map_err(AuthError::DatabaseError)
+}
+
+/// Attach `account` of `provider` to the user who signed in with `current`.
+///
+/// The session is checked in the transaction creating the new login method, with the user's
+/// login methods locked, so it can't be unlinked in between.
+pub async fn link_provider<Account, Provider>(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    current: &UserAuthPairData,
+    provider: &Provider,
+    account: &Account,
+    context: &AuthContext,
+    audit_log: Option<&AuditLog>,
+) -> Result<UserAuthPairData, AuthError>
+where
+    Account: Send + Sync + Sized + Clone,
+    Provider: AuthProvider<Account>,
+{
+    let tx = db.begin().await.map_err(AuthError::DatabaseError)?;
+    UserAuthPairData::find_by_user_id_for_update(&tx, current.user_id)
+        .await
+        .map_err(AuthError::DatabaseError)?;
+    let current = check_session(&tx, current).await?;
+    let linked = match provider
+        .try_register_in(&tx, account, current.user_id, context)
+        .await
+    {
+        Err(AuthError::DatabaseError(err))
+            if matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) =>
+        {
+            return Err(AuthError::ConflictingAccount);
+        }
+        result => result?,
+    };
+    tx.commit().await.map_err(AuthError::DatabaseError)?;
+    if let Some(audit_log) = audit_log {
+        audit_log
+            .record(
+                context,
+                AuditEntry::new(AuditEventKind::ProviderLinked, &linked.auth_provider)
+                    .with_user(linked.user_id)
+                    .with_subject(&linked.auth_key),
+            )
+            .await;
+    }
+    Ok(linked)
+}
+
+/// Detach `target` from the user who signed in with `current`.
+///
+/// The last verified login method of a user can't be removed, otherwise nobody could sign in
+/// as them. The user's login methods are locked while checking, so concurrent unlinks can't
+/// remove them all.
+pub async fn unlink_provider<Account, Provider>(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    current: &UserAuthPairData,
+    provider: &Provider,
+    target: &UserAuthPairData,
+    context: &AuthContext,
+    audit_log: Option<&AuditLog>,
+) -> Result<(), AuthError>
+where
+    Account: Send + Sync + Sized + Clone,
+    Provider: AuthProvider<Account>,
+{
+    let tx = db.begin().await.map_err(AuthError::DatabaseError)?;
+    let linked = UserAuthPairData::find_by_user_id_for_update(&tx, current.user_id)
+        .await
+        .map_err(AuthError::DatabaseError)?;
+    check_session(&tx, current).await?;
+    let Some(target) = linked
+        .iter()
+        .find(|pair| pair.id_number == target.id_number)
+    else {
+        return Err(AuthError::NotLinked);
+    };
+    let has_other_verified = linked
+        .iter()
+        .any(|pair| pair.is_verified && pair.id_number != target.id_number);
+    if !has_other_verified {
+        return Err(AuthError::LastLoginMethod);
+    }
+    provider.try_unlink_in(&tx, target).await?;
+    tx.commit().await.map_err(AuthError::DatabaseError)?;
+    if let Some(audit_log) = audit_log {
+        audit_log
+            .record(
+                context,
+                AuditEntry::new(AuditEventKind::ProviderUnlinked, &target.auth_provider)
+                    .with_user(target.user_id)
+                    .with_subject(&target.auth_key),
+            )
+            .await;
+    }
+    Ok(())
+}
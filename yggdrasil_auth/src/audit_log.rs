@@ -0,0 +1,185 @@
+use crate::auth_provider::{AuthContext, AuthError};
+use crate::repository::{AuditEventKind, AuditLogBeforeInsert, AuditLogData};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbErr, TransactionTrait};
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+/// One authentication event, completed with the request context when recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub kind: AuditEventKind,
+    pub auth_provider: String,
+    pub user_id: Option<Uuid>,
+    pub subject: Option<String>,
+    pub detail: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(kind: AuditEventKind, auth_provider: &str) -> Self {
+        Self {
+            kind,
+            auth_provider: auth_provider.to_owned(),
+            user_id: None,
+            subject: None,
+            detail: None,
+        }
+    }
+
+    pub fn with_user(mut self, user_id: Uuid) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    pub fn with_subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_owned());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: &str) -> Self {
+        self.detail = Some(detail.to_owned());
+        self
+    }
+}
+
+/// Records authentication events in `ygg_auth__audit_log`.
+///
+/// Recording never fails the operation being audited, errors are only logged.
+pub struct AuditLog {
+    database_connection: Arc<DatabaseConnection>,
+    retention: Option<chrono::Duration>,
+}
+
+impl AuditLog {
+    pub fn new(database_connection: Arc<DatabaseConnection>) -> Self {
+        Self {
+            database_connection,
+            retention: None,
+        }
+    }
+
+    /// Keep events for `retention`, see [AuditLog::prune_expired].
+    pub fn with_retention(mut self, retention: chrono::Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    pub async fn record(&self, context: &AuthContext, entry: AuditEntry) {
+        let kind = entry.kind;
+        let result = insert_entry(self.database_connection.as_ref(), context, entry).await;
+        if let Err(err) = result {
+            warn_not_recorded(kind, err);
+        }
+    }
+
+    /// Same as [AuditLog::record], but inside `tx` so the event is committed or rolled back
+    /// together with the caller's changes.
+    ///
+    /// The event is inserted in a savepoint, so failing to record it leaves `tx` usable.
+    pub async fn record_in(
+        &self,
+        tx: &DatabaseTransaction,
+        context: &AuthContext,
+        entry: AuditEntry,
+    ) {
+        let kind = entry.kind;
+        let result = async {
+            let savepoint = tx.begin().await?;
+            insert_entry(&savepoint, context, entry).await?;
+            savepoint.commit().await
+        }
+        .await;
+        if let Err(err) = result {
+            warn_not_recorded(kind, err);
+        }
+    }
+
+    /// Events of `user_id` in `[from, to)`, newest first.
+    pub async fn for_user(
+        &self,
+        user_id: Uuid,
+        from: chrono::NaiveDateTime,
+        to: chrono::NaiveDateTime,
+    ) -> Result<Vec<AuditLogData>, AuthError> {
+        AuditLogData::find_by_user_in_range(self.database_connection.as_ref(), user_id, from, to)
+            .await
+            .map_err(AuthError::DatabaseError)
+    }
+
+    /// Events of every user in `[from, to)`, newest first.
+    pub async fn in_range(
+        &self,
+        from: chrono::NaiveDateTime,
+        to: chrono::NaiveDateTime,
+    ) -> Result<Vec<AuditLogData>, AuthError> {
+        AuditLogData::find_in_range(self.database_connection.as_ref(), from, to)
+            .await
+            .map_err(AuthError::DatabaseError)
+    }
+
+    pub async fn prune(&self, before: chrono::NaiveDateTime) -> Result<u64, AuthError> {
+        AuditLogData::delete_created_before(self.database_connection.as_ref(), before)
+            .await
+            .map(|result| result.rows_affected)
+            .map_err(AuthError::DatabaseError)
+    }
+
+    /// Remove events older than the configured retention, keeping everything without one.
+    pub async fn prune_expired(&self) -> Result<u64, AuthError> {
+        match self.retention {
+            Some(retention) => self.prune(chrono::Utc::now().naive_utc() - retention).await,
+            None => Ok(0),
+        }
+    }
+}
+
+async fn insert_entry(
+    db: &impl ConnectionTrait,
+    context: &AuthContext,
+    entry: AuditEntry,
+) -> Result<(), DbErr> {
+    AuditLogData::create(
+        db,
+        AuditLogBeforeInsert {
+            kind: entry.kind,
+            auth_provider: entry.auth_provider,
+            user_id: entry.user_id,
+            subject: entry.subject,
+            ip: context.ip.map(|ip| ip.to_string()),
+            user_agent: context.user_agent.clone(),
+            detail: entry.detail,
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+fn warn_not_recorded(kind: AuditEventKind, err: DbErr) {
+    warn!(
+        "Yggdrasil Auth Module: Failed to record audit event {:?}: {:?}",
+        kind, err
+    );
+}
+
+/// Record `entry` if the provider was configured with an audit log.
+pub(crate) async fn audit(
+    audit_log: &Option<Arc<AuditLog>>,
+    context: &AuthContext,
+    entry: AuditEntry,
+) {
+    if let Some(audit_log) = audit_log {
+        audit_log.record(context, entry).await;
+    }
+}
+
+/// Same as [audit], inside the caller's transaction.
+pub(crate) async fn audit_in(
+    audit_log: &Option<Arc<AuditLog>>,
+    tx: &DatabaseTransaction,
+    context: &AuthContext,
+    entry: AuditEntry,
+) {
+    if let Some(audit_log) = audit_log {
+        audit_log.record_in(tx, context, entry).await;
+    }
+}
@@ -0,0 +1,390 @@
+use super::{AuthContext, AuthError, AuthProvider, VerifyInfo};
+use crate::audit_log::{audit, audit_in, AuditEntry, AuditLog};
+use crate::authorization::permission_matches;
+use crate::registration::find_available_user;
+use crate::repository::{
+    ApiKeyBeforeInsert, ApiKeyData, AuditEventKind, UserAuthPairBeforeInsert, UserAuthPairData,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sea_orm::{DatabaseConnection, DatabaseTransaction, TransactionTrait};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// `auth_provider` in [UserAuthPairData]
+const API_KEY_PROVIDER_NAME: &str = "api_key_provider";
+
+const DEFAULT_KEY_PREFIX: &str = "ygg";
+
+/// A key which has been generated but not stored yet.
+///
+/// `key` is the only copy of the plaintext, show it to the user once and drop it.
+///
+/// Only [ApiKeyProvider::new_key] makes these, so every stored secret is a random one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewApiKey {
+    key: String,
+    key_id: String,
+    name: String,
+    scopes: Vec<String>,
+    expires_at: Option<chrono::NaiveDateTime>,
+}
+
+impl NewApiKey {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    pub fn expires_at(&self) -> Option<chrono::NaiveDateTime> {
+        self.expires_at
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiKeyAccount {
+    /// Store a key made by [ApiKeyProvider::new_key].
+    New(NewApiKey),
+    /// A key presented by a client, e.g. from the `Authorization` header.
+    Presented(String),
+}
+
+/// A key which passed [ApiKeyProvider::verify_key].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedApiKey {
+    pub pair: UserAuthPairData,
+    pub key: ApiKeyData,
+}
+
+impl VerifiedApiKey {
+    /// Whether one of the key's scopes covers `scope`, `*` segments match like permissions.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.key
+            .scope_list()
+            .iter()
+            .any(|granted| permission_matches(granted, scope))
+    }
+}
+
+/// API keys for machine to machine calls, formatted as `<prefix>_<key id>_<secret>`.
+///
+/// Only a SHA-256 hash of the secret is stored. The secret has 256 bits of entropy,
+/// so a slow password hash is not needed.
+pub struct ApiKeyProvider {
+    database_connection: Arc<DatabaseConnection>,
+    key_prefix: String,
+    audit_log: Option<Arc<AuditLog>>,
+}
+
+fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+/// Compare without returning early, so timing does not reveal the matching prefix.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl ApiKeyProvider {
+    pub fn new(database_connection: Arc<DatabaseConnection>) -> Self {
+        Self {
+            database_connection,
+            key_prefix: DEFAULT_KEY_PREFIX.to_owned(),
+            audit_log: None,
+        }
+    }
+
+    /// Record authentication events in `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Prefix of generated keys, which makes them easy to recognize by secret scanners.
+    pub fn with_key_prefix(mut self, key_prefix: &str) -> Self {
+        self.key_prefix = key_prefix.to_owned();
+        self
+    }
+
+    /// Generate a key to be stored with [AuthProvider::try_register].
+    pub fn new_key(
+        &self,
+        name: &str,
+        scopes: Vec<String>,
+        expires_at: Option<chrono::NaiveDateTime>,
+    ) -> NewApiKey {
+        let mut id_bytes = [0u8; 6];
+        OsRng.fill_bytes(&mut id_bytes);
+        let key_id: String = id_bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let mut secret_bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut secret_bytes);
+        let secret = URL_SAFE_NO_PAD.encode(secret_bytes);
+        NewApiKey {
+            key: format!("{}_{}_{}", self.key_prefix, key_id, secret),
+            key_id,
+            name: name.to_owned(),
+            scopes,
+            expires_at,
+        }
+    }
+
+    /// Generate and store a key for `user_id`, returning the plaintext key.
+    pub async fn generate_key(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        scopes: Vec<String>,
+        expires_at: Option<chrono::NaiveDateTime>,
+        context: &AuthContext,
+    ) -> Result<(String, UserAuthPairData), AuthError> {
+        let new_key = self.new_key(name, scopes, expires_at);
+        let key = new_key.key.clone();
+        let pair = self
+            .try_register(&ApiKeyAccount::New(new_key), user_id, context)
+            .await?;
+        Ok((key, pair))
+    }
+
+    fn split_key<'a>(&self, key: &'a str) -> Option<(&'a str, &'a str)> {
+        let rest = key.strip_prefix(&self.key_prefix)?.strip_prefix('_')?;
+        rest.split_once('_')
+    }
+
+    /// Check a presented key, returning it with its pair if it is valid, not revoked and not
+    /// expired. The last used timestamp is updated on success.
+    pub async fn verify_key(&self, key: &str) -> Result<Option<VerifiedApiKey>, AuthError> {
+        let Some((key_id, secret)) = self.split_key(key) else {
+            return Ok(None);
+        };
+        let db = self.database_connection.as_ref();
+        let maybe_key = ApiKeyData::find_by_key_id(db, key_id)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let Some(stored) = maybe_key else {
+            return Ok(None);
+        };
+        if !constant_time_eq(hash_secret(secret).as_bytes(), stored.key_hash.as_bytes())
+            || !stored.is_usable_at(chrono::Utc::now().naive_utc())
+        {
+            return Ok(None);
+        }
+        let maybe_pair =
+            UserAuthPairData::find_by_key(db, API_KEY_PROVIDER_NAME, &stored.auth_key.to_string())
+                .await
+                .map_err(AuthError::DatabaseError)?;
+        let Some(pair) = maybe_pair else {
+            return Ok(None);
+        };
+        let stored = ApiKeyData::touch_last_used(db, &stored)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        Ok(Some(VerifiedApiKey { pair, key: stored }))
+    }
+
+    /// Keys of `user_id`, including revoked and expired ones.
+    pub async fn list_keys(&self, user_id: Uuid) -> Result<Vec<ApiKeyData>, AuthError> {
+        ApiKeyData::find_by_user_id(self.database_connection.as_ref(), user_id)
+            .await
+            .map_err(AuthError::DatabaseError)
+    }
+
+    /// Stop accepting the key while keeping it in listings.
+    pub async fn revoke_key(&self, key_id: &str) -> Result<bool, AuthError> {
+        let db = self.database_connection.as_ref();
+        let maybe_key = ApiKeyData::find_by_key_id(db, key_id)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        match maybe_key {
+            Some(stored) if stored.revoked_at.is_none() => {
+                ApiKeyData::revoke(db, &stored)
+                    .await
+                    .map_err(AuthError::DatabaseError)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthProvider<ApiKeyAccount> for ApiKeyProvider {
+    async fn try_login(
+        &self,
+        account: &ApiKeyAccount,
+        context: &AuthContext,
+    ) -> Result<Option<UserAuthPairData>, AuthError> {
+        let ApiKeyAccount::Presented(key) = account else {
+            return Ok(None);
+        };
+        let maybe_verified = self.verify_key(key).await?;
+        if let Some(verified) = &maybe_verified {
+            find_available_user(self.database_connection.as_ref(), verified.pair.user_id).await?;
+        }
+        // The pair returned here doesn't carry the scopes, so keys limited by them would
+        // act with every permission of the user. Those must go through verify_key.
+        let is_scoped = maybe_verified
+            .as_ref()
+            .is_some_and(|verified| !verified.has_scope("*"));
+        // Only the public key id is recorded, never the secret.
+        let key_id = self.split_key(key).map_or("", |(key_id, _)| key_id);
+        let entry = match &maybe_verified {
+            Some(verified) if !is_scoped => {
+                AuditEntry::new(AuditEventKind::LoginSucceeded, API_KEY_PROVIDER_NAME)
+                    .with_user(verified.pair.user_id)
+            }
+            _ => AuditEntry::new(AuditEventKind::LoginFailed, API_KEY_PROVIDER_NAME),
+        };
+        audit(&self.audit_log, context, entry.with_subject(key_id)).await;
+        if is_scoped {
+            return Err(AuthError::InvalidApiKey(
+                "Scoped API keys must be checked with ApiKeyProvider::verify_key".to_owned(),
+            ));
+        }
+        Ok(maybe_verified.map(|verified| verified.pair))
+    }
+
+    async fn try_register(
+        &self,
+        account: &ApiKeyAccount,
+        user_id: Uuid,
+        context: &AuthContext,
+    ) -> Result<UserAuthPairData, AuthError> {
+        let tx = self
+            .database_connection
+            .begin()
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let pair = self.try_register_in(&tx, account, user_id, context).await?;
+        tx.commit().await.map_err(AuthError::DatabaseError)?;
+        Ok(pair)
+    }
+
+    async fn try_register_in(
+        &self,
+        tx: &DatabaseTransaction,
+        account: &ApiKeyAccount,
+        user_id: Uuid,
+        context: &AuthContext,
+    ) -> Result<UserAuthPairData, AuthError> {
+        let ApiKeyAccount::New(new_key) = account else {
+            return Err(AuthError::InvalidApiKey(
+                "Only newly generated API keys can be registered".to_owned(),
+            ));
+        };
+        let Some((key_id, secret)) = self.split_key(&new_key.key) else {
+            return Err(AuthError::InvalidApiKey(
+                "API key was not generated by this provider".to_owned(),
+            ));
+        };
+        if key_id != new_key.key_id {
+            return Err(AuthError::InvalidApiKey(
+                "API key id does not match the key".to_owned(),
+            ));
+        }
+
+        let random_auth_key = Uuid::new_v4();
+        let key_record = ApiKeyBeforeInsert {
+            key_id: new_key.key_id.clone(),
+            key_hash: hash_secret(secret),
+            auth_key: random_auth_key,
+            user_id,
+            name: new_key.name.clone(),
+            scopes: new_key.scopes.join(" "),
+            expires_at: new_key.expires_at,
+        };
+        let pair = UserAuthPairBeforeInsert {
+            auth_provider: API_KEY_PROVIDER_NAME.to_owned(),
+            auth_key: random_auth_key.to_string(),
+            user_id,
+        };
+        ApiKeyData::create(tx, key_record)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let pair = UserAuthPairData::create(tx, pair)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        // The key is issued to an already authenticated user, nothing is left to verify.
+        let pair = UserAuthPairData::update_is_verified(tx, &pair, true)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        audit_in(
+            &self.audit_log,
+            tx,
+            context,
+            AuditEntry::new(AuditEventKind::Registered, API_KEY_PROVIDER_NAME)
+                .with_user(user_id)
+                .with_subject(&new_key.key_id),
+        )
+        .await;
+        Ok(pair)
+    }
+
+    /// API keys have no out-of-band verification, so there is nothing to send.
+    async fn send_verify(
+        &self,
+        _account: &ApiKeyAccount,
+        _verify_info: &VerifyInfo,
+    ) -> Result<(), AuthError> {
+        Ok(())
+    }
+
+    /// API keys have no verification codes, pairs are verified when the key is issued.
+    async fn check_verify_response(
+        &self,
+        _account: &ApiKeyAccount,
+        _verify_code: &str,
+        _context: &AuthContext,
+    ) -> Result<bool, AuthError> {
+        Ok(false)
+    }
+
+    async fn try_unlink(&self, pair: &UserAuthPairData) -> Result<(), AuthError> {
+        let tx = self
+            .database_connection
+            .begin()
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        self.try_unlink_in(&tx, pair).await?;
+        tx.commit().await.map_err(AuthError::DatabaseError)
+    }
+
+    async fn try_unlink_in(
+        &self,
+        tx: &DatabaseTransaction,
+        pair: &UserAuthPairData,
+    ) -> Result<(), AuthError> {
+        let auth_key = match Uuid::parse_str(&pair.auth_key) {
+            Ok(auth_key) if pair.auth_provider == API_KEY_PROVIDER_NAME => auth_key,
+            _ => return Err(AuthError::NotLinked),
+        };
+        let maybe_key = ApiKeyData::find_by_auth_key(tx, auth_key)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        if let Some(stored) = maybe_key {
+            ApiKeyData::delete(tx, stored)
+                .await
+                .map_err(AuthError::DatabaseError)?;
+        }
+        UserAuthPairData::delete(tx, pair.clone())
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        Ok(())
+    }
+}
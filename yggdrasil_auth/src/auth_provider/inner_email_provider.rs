@@ -1,34 +1,49 @@
-use super::{AuthError, AuthProvider, VerifyInfo};
+use super::{AuthContext, AuthError, AuthProvider, VerifyInfo};
+use crate::audit_log::{audit, audit_in, AuditEntry, AuditLog};
+use crate::login_throttle::LoginThrottle;
+pub use crate::mailer::{EmailContent, FileMailer, Mailer, MemoryMailer, SmtpMailer};
 pub use crate::password_hash::{
-    HashError, HashFunction, IntoHashedFunction, VerifyHashedFunction, ARGON2_HASH_FUNCTION,
-    BCRYPT_HASH_FUNCTION,
+    Argon2idHasher, BcryptHasher, HashError, MultiPasswordHasher, PasswordHasher, ScryptHasher,
 };
+use crate::registration::find_available_user;
 use crate::repository::{
-    InnerEmailProviderBeforeInsert, InnerEmailProviderData, UserAuthPairBeforeInsert,
-    UserAuthPairData,
+    AuditEventKind, InnerEmailProviderBeforeInsert, InnerEmailProviderData, MagicLinkBeforeInsert,
+    MagicLinkData, UserAuthPairBeforeInsert, UserAuthPairData,
 };
-use lettre::message::header::ContentType;
-use lettre::{Message, SmtpTransport, Transport};
-use sea_orm::{DatabaseConnection, TransactionError, TransactionTrait};
+use crate::verify_code::{VerifyCodePolicy, VerifyCodeStatus};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sea_orm::{DatabaseConnection, DatabaseTransaction, TransactionTrait};
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
+use tracing::warn;
 use uuid::Uuid;
+use yggdrasil_user::repository::{UserBeforeInsert, UserData};
 
 /// `auth_provider` in [UserAuthPairData]
 const INNER_EMAIL_PROVIDER_NAME: &str = "inner_email_provider";
 
 pub struct InnerEmailProvider {
-    hash_function: &'static HashFunction,
+    password_hasher: Arc<dyn PasswordHasher>,
     database_connection: Arc<DatabaseConnection>,
     template: EmailTemplateFunction,
-    mailer: Arc<SmtpTransport>,
+    mailer: Arc<dyn Mailer>,
+    login_throttle: Option<Arc<LoginThrottle>>,
+    audit_log: Option<Arc<AuditLog>>,
+    magic_link: Option<MagicLinkSettings>,
+    verify_code_policy: VerifyCodePolicy,
 }
 
-pub struct EmailContent {
-    pub subject: String,
-    pub from: String,
-    pub to: String,
-    pub content: String,
-    pub content_type: ContentType,
+/// Passwordless sign in through single-use links sent by email.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MagicLinkSettings {
+    /// The token is appended to this, e.g. `https://example.com/login/magic?token=`.
+    pub link_base: String,
+    pub token_ttl: chrono::Duration,
+    /// Create a user for emails without an account when their link is used.
+    pub auto_register: bool,
 }
 
 pub type EmailTemplateFunction = fn(&VerifyInfo, &EmailAccount) -> EmailContent;
@@ -41,18 +56,345 @@ pub struct EmailAccount {
 
 impl InnerEmailProvider {
     pub fn new(
-        hash_function: &'static HashFunction,
+        password_hasher: Arc<dyn PasswordHasher>,
         database_connection: Arc<DatabaseConnection>,
         template: EmailTemplateFunction,
-        mailer: Arc<SmtpTransport>,
+        mailer: Arc<dyn Mailer>,
     ) -> Self {
         Self {
-            hash_function,
+            password_hasher,
             database_connection,
             template,
             mailer,
+            login_throttle: None,
+            audit_log: None,
+            magic_link: None,
+            verify_code_policy: VerifyCodePolicy::default(),
         }
     }
+
+    /// Expiry and attempt limit of the codes sent by [AuthProvider::send_verify].
+    pub fn with_verify_code_policy(mut self, policy: VerifyCodePolicy) -> Self {
+        self.verify_code_policy = policy;
+        self
+    }
+
+    /// Check `verify_code` against the code sent to `user_record`, burning it when accepted.
+    ///
+    /// Every presented code counts against the attempt limit before it's compared, so
+    /// parallel guesses can't exceed the limit.
+    async fn check_code(
+        &self,
+        user_record: &InnerEmailProviderData,
+        verify_code: &str,
+    ) -> Result<bool, AuthError> {
+        let db = self.database_connection.as_ref();
+        let status = self.verify_code_policy.check(
+            user_record.verify_code.as_deref(),
+            user_record.code_sent_at,
+            user_record.verify_attempts,
+            verify_code,
+            chrono::Utc::now().naive_utc(),
+        );
+        if !matches!(
+            status,
+            VerifyCodeStatus::Accepted | VerifyCodeStatus::Rejected
+        ) {
+            return status.into_result();
+        }
+        let is_counted = InnerEmailProviderData::record_verify_attempt(
+            db,
+            user_record,
+            self.verify_code_policy.max_attempts,
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        if !is_counted {
+            return Err(AuthError::CodeAttemptsExhausted);
+        }
+        if status == VerifyCodeStatus::Rejected {
+            return Ok(false);
+        }
+        InnerEmailProviderData::consume_verify_code(db, user_record, verify_code)
+            .await
+            .map_err(AuthError::DatabaseError)
+    }
+
+    /// Allow signing in with links sent by [InnerEmailProvider::request_magic_link].
+    pub fn with_magic_link(mut self, settings: MagicLinkSettings) -> Self {
+        self.magic_link = Some(settings);
+        self
+    }
+
+    /// Rate limit password guesses with `login_throttle`.
+    pub fn with_login_throttle(mut self, login_throttle: LoginThrottle) -> Self {
+        self.login_throttle = Some(Arc::new(login_throttle));
+        self
+    }
+
+    /// Record authentication events in `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Count a failed login against the throttle and record it.
+    async fn login_failed(&self, email: &str, context: &AuthContext) -> Result<(), AuthError> {
+        audit(
+            &self.audit_log,
+            context,
+            AuditEntry::new(AuditEventKind::LoginFailed, INNER_EMAIL_PROVIDER_NAME)
+                .with_subject(email),
+        )
+        .await;
+        let Some(throttle) = &self.login_throttle else {
+            return Ok(());
+        };
+        if let Some(locked_until) = throttle.record_failure(email, context.ip).await? {
+            audit(
+                &self.audit_log,
+                context,
+                AuditEntry::new(AuditEventKind::LockedOut, INNER_EMAIL_PROVIDER_NAME)
+                    .with_subject(email)
+                    .with_detail(&format!("locked until {}", locked_until)),
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    /// Store `password` hashed with the current algorithm and parameters. Failures are only
+    /// logged since the user has already proven the password.
+    async fn rehash_password(&self, user_record: &InnerEmailProviderData, password: &str) {
+        let hashed_password = match self.password_hasher.hash(password) {
+            Ok(hashed_password) => hashed_password,
+            Err(_) => {
+                warn!(
+                    "Yggdrasil Auth Module: Failed to rehash the password of {}.",
+                    user_record.email
+                );
+                return;
+            }
+        };
+        if let Err(err) = InnerEmailProviderData::update_password_hash(
+            self.database_connection.as_ref(),
+            user_record,
+            &hashed_password,
+        )
+        .await
+        {
+            warn!(
+                "Yggdrasil Auth Module: Failed to store the rehashed password of {}: {:?}",
+                user_record.email, err
+            );
+        }
+    }
+
+    /// Replace the password of `email` after checking the code sent by [AuthProvider::send_verify].
+    ///
+    /// Returns `false` if the account doesn't exist or the code is wrong. A successful
+    /// reset also lifts a lockout imposed by the login throttle.
+    pub async fn reset_password(
+        &self,
+        email: &str,
+        verify_code: &str,
+        new_password: &str,
+        context: &AuthContext,
+    ) -> Result<bool, AuthError> {
+        let maybe_user_record =
+            InnerEmailProviderData::find_by_email(self.database_connection.as_ref(), email)
+                .await
+                .map_err(AuthError::DatabaseError)?;
+        let Some(user_record) = maybe_user_record else {
+            return Ok(false);
+        };
+        if !self.check_code(&user_record, verify_code).await? {
+            return Ok(false);
+        }
+        let hashed_password = self.password_hasher.hash(new_password)?;
+        let user_record = InnerEmailProviderData::update_password_hash(
+            self.database_connection.as_ref(),
+            &user_record,
+            &hashed_password,
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        if let Some(throttle) = &self.login_throttle {
+            throttle.clear_email(email).await?;
+        }
+        let maybe_pair = UserAuthPairData::find_by_key(
+            self.database_connection.as_ref(),
+            INNER_EMAIL_PROVIDER_NAME,
+            &user_record.auth_key.to_string(),
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        let mut entry = AuditEntry::new(AuditEventKind::PasswordChanged, INNER_EMAIL_PROVIDER_NAME)
+            .with_subject(email);
+        if let Some(pair) = maybe_pair {
+            entry = entry.with_user(pair.user_id);
+        }
+        audit(&self.audit_log, context, entry).await;
+        Ok(true)
+    }
+
+    fn magic_link_settings(&self) -> Result<&MagicLinkSettings, AuthError> {
+        self.magic_link
+            .as_ref()
+            .ok_or(AuthError::FeatureDisabled("Magic link"))
+    }
+
+    /// Send a sign-in link to `email` through the template and mailer.
+    ///
+    /// The link is passed to the template as [VerifyInfo::verify_code]. Nothing is sent to
+    /// unknown emails unless auto registration is enabled, without telling the caller, so
+    /// the request can't be used to probe for accounts.
+    pub async fn request_magic_link(
+        &self,
+        email: &str,
+        service_name: &str,
+    ) -> Result<(), AuthError> {
+        let settings = self.magic_link_settings()?;
+        if !settings.auto_register {
+            let maybe_user_record =
+                InnerEmailProviderData::find_by_email(self.database_connection.as_ref(), email)
+                    .await
+                    .map_err(AuthError::DatabaseError)?;
+            if maybe_user_record.is_none() {
+                return Ok(());
+            }
+        }
+
+        let mut token_bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut token_bytes);
+        let token = URL_SAFE_NO_PAD.encode(token_bytes);
+        MagicLinkData::create(
+            self.database_connection.as_ref(),
+            MagicLinkBeforeInsert {
+                token_hash: format!("{:x}", Sha256::digest(token.as_bytes())),
+                email: email.to_owned(),
+                expires_at: chrono::Utc::now().naive_utc() + settings.token_ttl,
+            },
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+
+        let verify_info = VerifyInfo {
+            verify_code: format!("{}{}", settings.link_base, token),
+            service_name: service_name.to_owned(),
+            user_account_description: email.to_owned(),
+        };
+        let account = EmailAccount {
+            email: email.to_owned(),
+            password: String::new(),
+        };
+        self.mailer
+            .send((self.template)(&verify_info, &account))
+            .await
+    }
+
+    /// Exchange the token of a magic link for the login it was sent to.
+    ///
+    /// Returns `None` if the token is unknown, expired or already used. Using the link
+    /// proves ownership of the email, so the pair is marked as verified.
+    pub async fn exchange_magic_link(
+        &self,
+        token: &str,
+        context: &AuthContext,
+    ) -> Result<Option<UserAuthPairData>, AuthError> {
+        let settings = self.magic_link_settings()?;
+        let token_hash = format!("{:x}", Sha256::digest(token.as_bytes()));
+        let tx = self
+            .database_connection
+            .begin()
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let maybe_link = MagicLinkData::take(&tx, &token_hash)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let now = chrono::Utc::now().naive_utc();
+        let Some(link) = maybe_link.filter(|link| link.expires_at >= now) else {
+            tx.commit().await.map_err(AuthError::DatabaseError)?;
+            audit(
+                &self.audit_log,
+                context,
+                AuditEntry::new(AuditEventKind::LoginFailed, INNER_EMAIL_PROVIDER_NAME)
+                    .with_detail("unknown or expired magic link"),
+            )
+            .await;
+            return Ok(None);
+        };
+
+        let maybe_user_record = InnerEmailProviderData::find_by_email(&tx, &link.email)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let maybe_pair = match maybe_user_record {
+            Some(user_record) => UserAuthPairData::find_by_key(
+                &tx,
+                INNER_EMAIL_PROVIDER_NAME,
+                &user_record.auth_key.to_string(),
+            )
+            .await
+            .map_err(AuthError::DatabaseError)?,
+            None if settings.auto_register => {
+                let display_name = link.email.split('@').next().unwrap_or_default();
+                let user = UserData::create(
+                    &tx,
+                    UserBeforeInsert {
+                        display_name: display_name.to_owned(),
+                        is_service_account: false,
+                    },
+                )
+                .await
+                .map_err(AuthError::DatabaseError)?;
+                // Nobody knows this password, the user can set one with a password reset.
+                let mut password_bytes = [0u8; 32];
+                OsRng.fill_bytes(&mut password_bytes);
+                let account = EmailAccount {
+                    email: link.email.clone(),
+                    password: URL_SAFE_NO_PAD.encode(password_bytes),
+                };
+                Some(
+                    self.try_register_in(&tx, &account, user.id, context)
+                        .await?,
+                )
+            }
+            None => None,
+        };
+        let Some(pair) = maybe_pair else {
+            tx.commit().await.map_err(AuthError::DatabaseError)?;
+            return Ok(None);
+        };
+        find_available_user(&tx, pair.user_id).await?;
+        let pair = match pair.is_verified {
+            true => pair,
+            false => UserAuthPairData::update_is_verified(&tx, &pair, true)
+                .await
+                .map_err(AuthError::DatabaseError)?,
+        };
+        tx.commit().await.map_err(AuthError::DatabaseError)?;
+        audit(
+            &self.audit_log,
+            context,
+            AuditEntry::new(AuditEventKind::LoginSucceeded, INNER_EMAIL_PROVIDER_NAME)
+                .with_user(pair.user_id)
+                .with_subject(&link.email)
+                .with_detail("magic link"),
+        )
+        .await;
+        Ok(Some(pair))
+    }
+
+    /// Remove magic links which expired without being used.
+    pub async fn delete_expired_magic_links(&self) -> Result<u64, AuthError> {
+        MagicLinkData::delete_expired(
+            self.database_connection.as_ref(),
+            chrono::Utc::now().naive_utc(),
+        )
+        .await
+        .map(|result| result.rows_affected)
+        .map_err(AuthError::DatabaseError)
+    }
 }
 
 #[async_trait::async_trait]
@@ -60,50 +402,93 @@ impl AuthProvider<EmailAccount> for InnerEmailProvider {
     async fn try_login(
         &self,
         account: &EmailAccount,
+        context: &AuthContext,
     ) -> Result<Option<UserAuthPairData>, AuthError> {
         let EmailAccount { email, password } = account;
+        if let Some(throttle) = &self.login_throttle {
+            throttle.check(email, context.ip).await?;
+        }
         let maybe_user_record =
             InnerEmailProviderData::find_by_email(self.database_connection.as_ref(), email)
                 .await
                 .map_err(AuthError::DatabaseError)?;
 
-        if maybe_user_record.is_none() {
+        let is_password_correct = match &maybe_user_record {
+            Some(user_record) => self
+                .password_hasher
+                .verify(password, &user_record.password_hash)?,
+            None => false,
+        };
+        if !is_password_correct {
+            self.login_failed(email, context).await?;
             return Ok(None);
         }
+        if let Some(throttle) = &self.login_throttle {
+            throttle.clear_email(email).await?;
+        }
         let user_record = maybe_user_record.unwrap();
-        let is_password_correct =
-            (self.hash_function.verify)(&password, &user_record.password_hash)
-                .map_err(|e| e.into())?;
-        if !is_password_correct {
-            return Ok(None);
+        if self
+            .password_hasher
+            .needs_rehash(&user_record.password_hash)
+        {
+            self.rehash_password(&user_record, password).await;
         }
         let auth_key = user_record.auth_key;
 
-        UserAuthPairData::find_by_key(
+        let maybe_pair = UserAuthPairData::find_by_key(
             self.database_connection.as_ref(),
             INNER_EMAIL_PROVIDER_NAME,
             &auth_key.to_string(),
         )
         .await
-        .map_err(AuthError::DatabaseError)
+        .map_err(AuthError::DatabaseError)?;
+        if let Some(pair) = &maybe_pair {
+            find_available_user(self.database_connection.as_ref(), pair.user_id).await?;
+            audit(
+                &self.audit_log,
+                context,
+                AuditEntry::new(AuditEventKind::LoginSucceeded, INNER_EMAIL_PROVIDER_NAME)
+                    .with_user(pair.user_id)
+                    .with_subject(email),
+            )
+            .await;
+        }
+        Ok(maybe_pair)
     }
 
     async fn try_register(
         &self,
         account: &EmailAccount,
         user_id: Uuid,
+        context: &AuthContext,
+    ) -> Result<UserAuthPairData, AuthError> {
+        let tx = self
+            .database_connection
+            .begin()
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let pair = self.try_register_in(&tx, account, user_id, context).await?;
+        tx.commit().await.map_err(AuthError::DatabaseError)?;
+        Ok(pair)
+    }
+
+    async fn try_register_in(
+        &self,
+        tx: &DatabaseTransaction,
+        account: &EmailAccount,
+        user_id: Uuid,
+        context: &AuthContext,
     ) -> Result<UserAuthPairData, AuthError> {
         let EmailAccount { email, password } = account;
-        let maybe_user_record =
-            InnerEmailProviderData::find_by_email(self.database_connection.as_ref(), email)
-                .await
-                .map_err(AuthError::DatabaseError)?;
+        let maybe_user_record = InnerEmailProviderData::find_by_email(tx, email)
+            .await
+            .map_err(AuthError::DatabaseError)?;
         if maybe_user_record.is_some() {
             return Err(AuthError::ConflictingAccount);
         }
 
         let random_auth_key = uuid::Uuid::new_v4();
-        let hashed_password = (self.hash_function.into_hashed)(password).map_err(|e| e.into())?;
+        let hashed_password = self.password_hasher.hash(password)?;
         let provider_record = InnerEmailProviderBeforeInsert {
             email: email.clone(),
             password_hash: hashed_password,
@@ -114,78 +499,107 @@ impl AuthProvider<EmailAccount> for InnerEmailProvider {
             auth_key: random_auth_key.to_string(),
             user_id,
         };
-        let tr = self
-            .database_connection
-            .transaction(|tx| {
-                Box::pin(async move {
-                    InnerEmailProviderData::create(tx, provider_record)
-                        .await
-                        .map_err(AuthError::DatabaseError)?;
-                    UserAuthPairData::create(tx, pair)
-                        .await
-                        .map_err(AuthError::DatabaseError)
-                })
-            })
-            .await;
-        match tr {
-            Ok(pair) => Ok(pair),
-            Err(e) => match e {
-                TransactionError::Transaction(AuthError::DatabaseError(err)) => {
-                    Err(AuthError::DatabaseError(err))
-                }
-                TransactionError::Connection(err) => Err(AuthError::DatabaseError(err)),
-                _ => unreachable!(),
-            },
-        }
+        InnerEmailProviderData::create(tx, provider_record)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let pair = UserAuthPairData::create(tx, pair)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        audit_in(
+            &self.audit_log,
+            tx,
+            context,
+            AuditEntry::new(AuditEventKind::Registered, INNER_EMAIL_PROVIDER_NAME)
+                .with_user(user_id)
+                .with_subject(email),
+        )
+        .await;
+        Ok(pair)
     }
 
+    /// Store the code of `verify_info` for the account, if it exists, and email it.
     async fn send_verify(
         &self,
         account: &EmailAccount,
         verify_info: &VerifyInfo,
     ) -> Result<(), AuthError> {
-        let email_content = (self.template)(verify_info, account);
-        let email = Message::builder()
-            .from(
-                email_content
-                    .from
-                    .parse()
-                    .map_err(|_| AuthError::VerifySendError("Invalid from email".to_owned()))?,
+        let maybe_user_record = InnerEmailProviderData::find_by_email(
+            self.database_connection.as_ref(),
+            &account.email,
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        if let Some(user_record) = maybe_user_record {
+            InnerEmailProviderData::set_verify_code(
+                self.database_connection.as_ref(),
+                &user_record,
+                verify_info.verify_code.clone(),
             )
-            .to(email_content
-                .to
-                .parse()
-                .map_err(|_| AuthError::VerifySendError("Invalid to email".to_owned()))?)
-            .subject(email_content.subject)
-            .header(email_content.content_type)
-            .body(email_content.content)
-            .map_err(|_| AuthError::VerifySendError("Failed to build email".to_owned()))?;
-        match self.mailer.send(&email) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(AuthError::VerifySendError(
-                "Failed to send email".to_owned(),
-            )),
+            .await
+            .map_err(AuthError::DatabaseError)?;
         }
+        let email_content = (self.template)(verify_info, account);
+        self.mailer.send(email_content).await
     }
 
     async fn check_verify_response(
         &self,
         account: &EmailAccount,
         verify_code: &str,
+        context: &AuthContext,
     ) -> Result<bool, AuthError> {
         let EmailAccount { email, .. } = account;
         let maybe_user_record =
             InnerEmailProviderData::find_by_email(self.database_connection.as_ref(), email)
                 .await
                 .map_err(AuthError::DatabaseError)?;
-        if maybe_user_record.is_none() {
-            return Ok(false);
-        }
-        let user_record = maybe_user_record.unwrap();
-        if user_record.verify_code.is_none() {
-            return Ok(false);
+        let is_code_correct = match &maybe_user_record {
+            Some(user_record) => self.check_code(user_record, verify_code).await?,
+            None => false,
+        };
+        let kind = match is_code_correct {
+            true => AuditEventKind::Verified,
+            false => AuditEventKind::VerificationFailed,
+        };
+        audit(
+            &self.audit_log,
+            context,
+            AuditEntry::new(kind, INNER_EMAIL_PROVIDER_NAME).with_subject(email),
+        )
+        .await;
+        Ok(is_code_correct)
+    }
+
+    async fn try_unlink(&self, pair: &UserAuthPairData) -> Result<(), AuthError> {
+        let tx = self
+            .database_connection
+            .begin()
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        self.try_unlink_in(&tx, pair).await?;
+        tx.commit().await.map_err(AuthError::DatabaseError)
+    }
+
+    async fn try_unlink_in(
+        &self,
+        tx: &DatabaseTransaction,
+        pair: &UserAuthPairData,
+    ) -> Result<(), AuthError> {
+        let auth_key = match Uuid::parse_str(&pair.auth_key) {
+            Ok(auth_key) if pair.auth_provider == INNER_EMAIL_PROVIDER_NAME => auth_key,
+            _ => return Err(AuthError::NotLinked),
+        };
+        let maybe_user_record = InnerEmailProviderData::find_by_auth_key(tx, auth_key)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        if let Some(user_record) = maybe_user_record {
+            InnerEmailProviderData::delete(tx, user_record)
+                .await
+                .map_err(AuthError::DatabaseError)?;
         }
-        let correct_code = user_record.verify_code.unwrap();
-        Ok(correct_code == verify_code)
+        UserAuthPairData::delete(tx, pair.clone())
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        Ok(())
     }
 }
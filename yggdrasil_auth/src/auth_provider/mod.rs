@@ -1,17 +1,120 @@
+pub mod api_key_provider;
 pub mod inner_email_provider;
+pub mod passkey_provider;
+pub mod phone_provider;
 
+use crate::password_hash::HashError;
 use crate::repository::UserAuthPairData;
-use sea_orm::DbErr;
-use std::fmt::{Display, Formatter};
+use sea_orm::{DatabaseTransaction, DbErr};
+use std::net::IpAddr;
 use uuid::Uuid;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, thiserror::Error)]
 pub enum AuthError {
-    ConnectionError(String),
-    DatabaseError(DbErr),
+    #[error("database error")]
+    DatabaseError(#[from] DbErr),
+    #[error("account already exists")]
     ConflictingAccount,
-    VerifySendError(String),
-    VerifyAlgorithmError(String),
+    /// The mailer or SMS gateway failed to deliver a message.
+    #[error("failed to deliver message: {message}")]
+    DeliveryFailed {
+        message: String,
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+    /// An email address which can't be parsed.
+    #[error("invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("password hashing failed")]
+    PasswordHash(#[from] HashError),
+    /// Malformed or mismatching WebAuthn data.
+    #[error("invalid passkey ceremony: {0}")]
+    InvalidCeremony(String),
+    #[error("invalid API key: {0}")]
+    InvalidApiKey(String),
+    /// The operation needs a feature the provider was not configured with.
+    #[error("{0} is not enabled")]
+    FeatureDisabled(&'static str),
+    #[error("verification code has expired")]
+    CodeExpired,
+    /// Too many wrong codes were presented, a new one has to be sent.
+    #[error("too many wrong verification codes")]
+    CodeAttemptsExhausted,
+    /// Too many failed attempts, the next one is accepted from `retry_at`.
+    #[error("too many login attempts, retry at {retry_at}")]
+    LoginThrottled { retry_at: chrono::NaiveDateTime },
+    /// The account is locked out until `until` or until its password is reset.
+    #[error("account locked until {until}")]
+    AccountLocked { until: chrono::NaiveDateTime },
+    /// The pair the operation was authorized with doesn't exist anymore or isn't verified.
+    #[error("current session is not verified")]
+    UnverifiedSession,
+    /// The pair doesn't belong to the user or to the provider.
+    #[error("login method is not linked to the user")]
+    NotLinked,
+    /// Removing the pair would leave the user without any way to sign in.
+    #[error("cannot remove the last login method")]
+    LastLoginMethod,
+    /// The pair points to a user which doesn't exist.
+    #[error("user not found")]
+    UserNotFound,
+    /// The user behind the login is suspended or deleted.
+    #[error("user is not available")]
+    UserUnavailable,
+    /// The user lacks the permission, e.g. `shop.production.write`.
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+    /// No role with the given name exists.
+    #[error("role not found: {0}")]
+    RoleNotFound(String),
+    /// The phone number could not be parsed or is not a valid number.
+    #[error("invalid phone number: {0}")]
+    InvalidPhoneNumber(String),
+}
+
+impl AuthError {
+    /// Stable identifier for API responses, unaffected by changes to the messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthError::DatabaseError(_) => "auth.database",
+            AuthError::ConflictingAccount => "auth.conflicting_account",
+            AuthError::DeliveryFailed { .. } => "auth.delivery_failed",
+            AuthError::InvalidAddress(_) => "auth.invalid_address",
+            AuthError::PasswordHash(_) => "auth.password_hash",
+            AuthError::InvalidCeremony(_) => "auth.invalid_ceremony",
+            AuthError::InvalidApiKey(_) => "auth.invalid_api_key",
+            AuthError::FeatureDisabled(_) => "auth.feature_disabled",
+            AuthError::CodeExpired => "auth.code_expired",
+            AuthError::CodeAttemptsExhausted => "auth.code_attempts_exhausted",
+            AuthError::LoginThrottled { .. } => "auth.login_throttled",
+            AuthError::AccountLocked { .. } => "auth.account_locked",
+            AuthError::UnverifiedSession => "auth.unverified_session",
+            AuthError::NotLinked => "auth.not_linked",
+            AuthError::LastLoginMethod => "auth.last_login_method",
+            AuthError::UserNotFound => "auth.user_not_found",
+            AuthError::UserUnavailable => "auth.user_unavailable",
+            AuthError::PermissionDenied(_) => "auth.permission_denied",
+            AuthError::RoleNotFound(_) => "auth.role_not_found",
+            AuthError::InvalidPhoneNumber(_) => "auth.invalid_phone_number",
+        }
+    }
+
+    pub(crate) fn delivery_failed(
+        message: &str,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        AuthError::DeliveryFailed {
+            message: message.to_owned(),
+            source: Some(Box::new(source)),
+        }
+    }
+}
+
+/// Where a request comes from, recorded in the audit log and used for throttling.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthContext {
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -21,27 +124,27 @@ pub struct VerifyInfo {
     pub user_account_description: String,
 }
 
-impl Display for AuthError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            AuthError::ConnectionError(msg) => write!(f, "Connection error: {}", msg),
-            AuthError::DatabaseError(err) => write!(f, "Database error: {:?}", err),
-            AuthError::VerifySendError(msg) => write!(f, "Verify send error: {}", msg),
-            AuthError::VerifyAlgorithmError(msg) => write!(f, "Verify algorithm error: {}", msg),
-            AuthError::ConflictingAccount => write!(f, "Conflicting account"),
-        }
-    }
-}
-
-impl std::error::Error for AuthError {}
-
 #[async_trait::async_trait]
 pub trait AuthProvider<Account: Send + Sync + Sized + Clone>: Send + Sync + Sized {
-    async fn try_login(&self, account: &Account) -> Result<Option<UserAuthPairData>, AuthError>;
+    async fn try_login(
+        &self,
+        account: &Account,
+        context: &AuthContext,
+    ) -> Result<Option<UserAuthPairData>, AuthError>;
     async fn try_register(
         &self,
         account: &Account,
         user_id: Uuid,
+        context: &AuthContext,
+    ) -> Result<UserAuthPairData, AuthError>;
+    /// Same as [AuthProvider::try_register], but inside the caller's transaction so the
+    /// account can be created together with other records.
+    async fn try_register_in(
+        &self,
+        tx: &DatabaseTransaction,
+        account: &Account,
+        user_id: Uuid,
+        context: &AuthContext,
     ) -> Result<UserAuthPairData, AuthError>;
     async fn send_verify(
         &self,
@@ -52,5 +155,14 @@ pub trait AuthProvider<Account: Send + Sync + Sized + Clone>: Send + Sync + Size
         &self,
         account: &Account,
         verify_code: &str,
+        context: &AuthContext,
     ) -> Result<bool, AuthError>;
+    /// Delete `pair` together with the provider's own record of the account.
+    async fn try_unlink(&self, pair: &UserAuthPairData) -> Result<(), AuthError>;
+    /// Same as [AuthProvider::try_unlink], but inside the caller's transaction.
+    async fn try_unlink_in(
+        &self,
+        tx: &DatabaseTransaction,
+        pair: &UserAuthPairData,
+    ) -> Result<(), AuthError>;
 }
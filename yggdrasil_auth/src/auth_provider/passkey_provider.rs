@@ -0,0 +1,579 @@
+use super::{AuthContext, AuthError, AuthProvider, VerifyInfo};
+use crate::audit_log::{audit, audit_in, AuditEntry, AuditLog};
+use crate::registration::find_available_user;
+use crate::repository::{
+    AuditEventKind, PasskeyChallengeBeforeInsert, PasskeyChallengeData,
+    PasskeyCredentialBeforeInsert, PasskeyCredentialData, UserAuthPairBeforeInsert,
+    UserAuthPairData,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ciborium::Value;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DatabaseTransaction, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+/// `auth_provider` in [UserAuthPairData]
+const PASSKEY_PROVIDER_NAME: &str = "passkey_provider";
+
+const REGISTRATION_CEREMONY: &str = "registration";
+const AUTHENTICATION_CEREMONY: &str = "authentication";
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// COSE algorithm identifier of ES256, the only algorithm accepted for now.
+const COSE_ALGORITHM_ES256: i128 = -7;
+const COSE_KEY_TYPE_EC2: i128 = 2;
+const COSE_CURVE_P256: i128 = 1;
+
+pub struct RelyingParty {
+    /// Effective domain the credentials are scoped to, e.g. `example.com`.
+    pub id: String,
+    /// Name shown to the user by the authenticator.
+    pub name: String,
+    /// Origin the browser reports in `clientDataJSON`, e.g. `https://example.com`.
+    pub origin: String,
+}
+
+/// Passkey (WebAuthn) provider.
+///
+/// Only ES256 credentials are accepted and attestation statements are not verified,
+/// which is what browsers produce with the default `attestation: "none"`.
+pub struct PasskeyProvider {
+    database_connection: Arc<DatabaseConnection>,
+    relying_party: RelyingParty,
+    challenge_timeout: chrono::Duration,
+    audit_log: Option<Arc<AuditLog>>,
+}
+
+/// Everything the client needs for `navigator.credentials.create()` or `get()`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PasskeyChallenge {
+    pub challenge: String,
+    pub relying_party_id: String,
+    pub relying_party_name: String,
+    pub user_id: Option<Uuid>,
+    pub timeout_ms: i64,
+}
+
+/// Response of `navigator.credentials.create()`, binary fields already base64url decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasskeyRegistration {
+    pub credential_id: String,
+    pub client_data_json: Vec<u8>,
+    pub attestation_object: Vec<u8>,
+}
+
+/// Response of `navigator.credentials.get()`, binary fields already base64url decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasskeyAssertion {
+    pub credential_id: String,
+    pub client_data_json: Vec<u8>,
+    pub authenticator_data: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasskeyAccount {
+    Registration(PasskeyRegistration),
+    Assertion(PasskeyAssertion),
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+}
+
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    /// Credential id and COSE public key, only present during registration.
+    attested_credential: Option<(&'a [u8], &'a [u8])>,
+}
+
+fn invalid_ceremony(msg: &str) -> AuthError {
+    AuthError::InvalidCeremony(msg.to_owned())
+}
+
+impl ClientData {
+    fn parse(raw: &[u8], expected_type: &str, expected_origin: &str) -> Result<Self, AuthError> {
+        let client_data: ClientData = serde_json::from_slice(raw)
+            .map_err(|_| invalid_ceremony("Malformed clientDataJSON"))?;
+        if client_data.ceremony_type != expected_type {
+            return Err(invalid_ceremony(
+                "Unexpected ceremony type in clientDataJSON",
+            ));
+        }
+        if client_data.origin != expected_origin {
+            return Err(invalid_ceremony("Unexpected origin in clientDataJSON"));
+        }
+        Ok(client_data)
+    }
+}
+
+impl<'a> AuthenticatorData<'a> {
+    fn parse(raw: &'a [u8]) -> Result<Self, AuthError> {
+        if raw.len() < 37 {
+            return Err(invalid_ceremony("Authenticator data is too short"));
+        }
+        let flags = raw[32];
+        let sign_count = u32::from_be_bytes([raw[33], raw[34], raw[35], raw[36]]);
+        let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+            // 16 bytes of AAGUID, then a big endian u16 credential id length.
+            let rest = &raw[37..];
+            if rest.len() < 18 {
+                return Err(invalid_ceremony("Attested credential data is too short"));
+            }
+            let id_length = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+            let rest = &rest[18..];
+            if rest.len() < id_length {
+                return Err(invalid_ceremony("Attested credential id is truncated"));
+            }
+            Some((&rest[..id_length], &rest[id_length..]))
+        } else {
+            None
+        };
+        Ok(Self {
+            rp_id_hash: &raw[..32],
+            flags,
+            sign_count,
+            attested_credential,
+        })
+    }
+}
+
+fn auth_data_from_attestation(attestation_object: &[u8]) -> Result<Vec<u8>, AuthError> {
+    let value: Value = ciborium::de::from_reader(attestation_object)
+        .map_err(|_| invalid_ceremony("Malformed attestation object"))?;
+    let entries = value
+        .into_map()
+        .map_err(|_| invalid_ceremony("Malformed attestation object"))?;
+    entries
+        .into_iter()
+        .find(|(key, _)| key.as_text() == Some("authData"))
+        .and_then(|(_, value)| value.into_bytes().ok())
+        .ok_or_else(|| invalid_ceremony("Attestation object has no authenticator data"))
+}
+
+fn verifying_key_from_cose(cose_key: &[u8]) -> Result<VerifyingKey, AuthError> {
+    let value: Value = ciborium::de::from_reader(cose_key)
+        .map_err(|_| invalid_ceremony("Malformed COSE public key"))?;
+    let entries = value
+        .into_map()
+        .map_err(|_| invalid_ceremony("Malformed COSE public key"))?;
+    let (mut key_type, mut algorithm, mut curve) = (None, None, None);
+    let (mut x, mut y) = (None, None);
+    for (label, value) in entries {
+        let Some(label) = label.as_integer().map(i128::from) else {
+            continue;
+        };
+        match label {
+            1 => key_type = value.as_integer().map(i128::from),
+            3 => algorithm = value.as_integer().map(i128::from),
+            -1 => curve = value.as_integer().map(i128::from),
+            -2 => x = value.into_bytes().ok(),
+            -3 => y = value.into_bytes().ok(),
+            _ => {}
+        }
+    }
+    if key_type != Some(COSE_KEY_TYPE_EC2)
+        || algorithm != Some(COSE_ALGORITHM_ES256)
+        || curve != Some(COSE_CURVE_P256)
+    {
+        return Err(invalid_ceremony("Only ES256 passkeys are supported"));
+    }
+    match (x, y) {
+        (Some(x), Some(y)) if x.len() == 32 && y.len() == 32 => {
+            let mut sec1 = Vec::with_capacity(65);
+            sec1.push(0x04);
+            sec1.extend_from_slice(&x);
+            sec1.extend_from_slice(&y);
+            VerifyingKey::from_sec1_bytes(&sec1)
+                .map_err(|_| invalid_ceremony("Invalid ES256 public key"))
+        }
+        _ => Err(invalid_ceremony("Invalid ES256 public key")),
+    }
+}
+
+impl PasskeyProvider {
+    pub fn new(
+        database_connection: Arc<DatabaseConnection>,
+        relying_party: RelyingParty,
+        challenge_timeout: chrono::Duration,
+    ) -> Self {
+        Self {
+            database_connection,
+            relying_party,
+            challenge_timeout,
+            audit_log: None,
+        }
+    }
+
+    /// Record authentication events in `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    async fn login_failed(&self, credential_id: &str, context: &AuthContext, detail: &str) {
+        audit(
+            &self.audit_log,
+            context,
+            AuditEntry::new(AuditEventKind::LoginFailed, PASSKEY_PROVIDER_NAME)
+                .with_subject(credential_id)
+                .with_detail(detail),
+        )
+        .await;
+    }
+
+    /// Issue a challenge for adding a passkey to `user_id`.
+    pub async fn start_registration(&self, user_id: Uuid) -> Result<PasskeyChallenge, AuthError> {
+        self.issue_challenge(REGISTRATION_CEREMONY, Some(user_id))
+            .await
+    }
+
+    /// Issue a challenge for signing in with any registered passkey.
+    pub async fn start_authentication(&self) -> Result<PasskeyChallenge, AuthError> {
+        self.issue_challenge(AUTHENTICATION_CEREMONY, None).await
+    }
+
+    /// Remove challenges which were never answered.
+    pub async fn delete_expired_challenges(&self) -> Result<u64, AuthError> {
+        let expired_before = chrono::Utc::now().naive_utc() - self.challenge_timeout;
+        PasskeyChallengeData::delete_created_before(
+            self.database_connection.as_ref(),
+            expired_before,
+        )
+        .await
+        .map(|result| result.rows_affected)
+        .map_err(AuthError::DatabaseError)
+    }
+
+    async fn issue_challenge(
+        &self,
+        ceremony: &str,
+        user_id: Option<Uuid>,
+    ) -> Result<PasskeyChallenge, AuthError> {
+        let mut random_bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut random_bytes);
+        let challenge = URL_SAFE_NO_PAD.encode(random_bytes);
+        PasskeyChallengeData::create(
+            self.database_connection.as_ref(),
+            PasskeyChallengeBeforeInsert {
+                challenge: challenge.clone(),
+                ceremony: ceremony.to_owned(),
+                user_id,
+            },
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        Ok(PasskeyChallenge {
+            challenge,
+            relying_party_id: self.relying_party.id.clone(),
+            relying_party_name: self.relying_party.name.clone(),
+            user_id,
+            timeout_ms: self.challenge_timeout.num_milliseconds(),
+        })
+    }
+
+    /// Consume the challenge echoed in `clientDataJSON`, ignoring it if it has expired or
+    /// was issued for the other ceremony.
+    async fn take_challenge(
+        &self,
+        db: &impl ConnectionTrait,
+        client_data: &ClientData,
+        ceremony: &str,
+    ) -> Result<Option<PasskeyChallengeData>, AuthError> {
+        let maybe_challenge = PasskeyChallengeData::take(db, &client_data.challenge)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let now = chrono::Utc::now().naive_utc();
+        Ok(maybe_challenge.filter(|challenge| {
+            challenge.ceremony == ceremony && challenge.created_at + self.challenge_timeout >= now
+        }))
+    }
+
+    fn check_relying_party(&self, auth_data: &AuthenticatorData) -> Result<(), AuthError> {
+        let expected_hash = Sha256::digest(self.relying_party.id.as_bytes());
+        if auth_data.rp_id_hash != expected_hash.as_slice() {
+            return Err(invalid_ceremony("Passkey belongs to another relying party"));
+        }
+        if auth_data.flags & FLAG_USER_PRESENT == 0 {
+            return Err(invalid_ceremony("User presence was not confirmed"));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthProvider<PasskeyAccount> for PasskeyProvider {
+    async fn try_login(
+        &self,
+        account: &PasskeyAccount,
+        context: &AuthContext,
+    ) -> Result<Option<UserAuthPairData>, AuthError> {
+        let PasskeyAccount::Assertion(assertion) = account else {
+            return Err(invalid_ceremony("Passkey login requires an assertion"));
+        };
+        let client_data = ClientData::parse(
+            &assertion.client_data_json,
+            "webauthn.get",
+            &self.relying_party.origin,
+        )?;
+        if self
+            .take_challenge(
+                self.database_connection.as_ref(),
+                &client_data,
+                AUTHENTICATION_CEREMONY,
+            )
+            .await?
+            .is_none()
+        {
+            self.login_failed(
+                &assertion.credential_id,
+                context,
+                "unknown or expired challenge",
+            )
+            .await;
+            return Ok(None);
+        }
+
+        let maybe_credential = PasskeyCredentialData::find_by_credential_id(
+            self.database_connection.as_ref(),
+            &assertion.credential_id,
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        let Some(credential) = maybe_credential else {
+            self.login_failed(&assertion.credential_id, context, "unknown credential")
+                .await;
+            return Ok(None);
+        };
+
+        let auth_data = AuthenticatorData::parse(&assertion.authenticator_data)?;
+        self.check_relying_party(&auth_data)?;
+        let public_key = VerifyingKey::from_sec1_bytes(&credential.public_key)
+            .map_err(|_| invalid_ceremony("Stored passkey public key is corrupted"))?;
+        let signature = Signature::from_der(&assertion.signature)
+            .map_err(|_| invalid_ceremony("Malformed assertion signature"))?;
+        let mut signed_data = assertion.authenticator_data.clone();
+        signed_data.extend_from_slice(&Sha256::digest(&assertion.client_data_json));
+        if public_key.verify(&signed_data, &signature).is_err() {
+            self.login_failed(&assertion.credential_id, context, "invalid signature")
+                .await;
+            return Ok(None);
+        }
+
+        // Authenticators without a counter always report zero.
+        let sign_count = i64::from(auth_data.sign_count);
+        if (sign_count != 0 || credential.sign_count != 0) && sign_count <= credential.sign_count {
+            warn!(
+                "Yggdrasil Auth Module: Sign counter of passkey {} did not increase, the authenticator may have been cloned.",
+                credential.credential_id
+            );
+            self.login_failed(
+                &assertion.credential_id,
+                context,
+                "sign counter did not increase",
+            )
+            .await;
+            return Ok(None);
+        }
+        let maybe_credential = PasskeyCredentialData::update_sign_count(
+            self.database_connection.as_ref(),
+            &credential,
+            sign_count,
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        // Another assertion with the same or a higher counter was accepted in the meantime.
+        let Some(credential) = maybe_credential else {
+            self.login_failed(
+                &assertion.credential_id,
+                context,
+                "sign counter did not increase",
+            )
+            .await;
+            return Ok(None);
+        };
+
+        let maybe_pair = UserAuthPairData::find_by_key(
+            self.database_connection.as_ref(),
+            PASSKEY_PROVIDER_NAME,
+            &credential.auth_key.to_string(),
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        if let Some(pair) = &maybe_pair {
+            find_available_user(self.database_connection.as_ref(), pair.user_id).await?;
+            audit(
+                &self.audit_log,
+                context,
+                AuditEntry::new(AuditEventKind::LoginSucceeded, PASSKEY_PROVIDER_NAME)
+                    .with_user(pair.user_id)
+                    .with_subject(&credential.credential_id),
+            )
+            .await;
+        }
+        Ok(maybe_pair)
+    }
+
+    async fn try_register(
+        &self,
+        account: &PasskeyAccount,
+        user_id: Uuid,
+        context: &AuthContext,
+    ) -> Result<UserAuthPairData, AuthError> {
+        let tx = self
+            .database_connection
+            .begin()
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let pair = self.try_register_in(&tx, account, user_id, context).await?;
+        tx.commit().await.map_err(AuthError::DatabaseError)?;
+        Ok(pair)
+    }
+
+    async fn try_register_in(
+        &self,
+        tx: &DatabaseTransaction,
+        account: &PasskeyAccount,
+        user_id: Uuid,
+        context: &AuthContext,
+    ) -> Result<UserAuthPairData, AuthError> {
+        let PasskeyAccount::Registration(registration) = account else {
+            return Err(invalid_ceremony(
+                "Passkey registration requires an attestation",
+            ));
+        };
+        let client_data = ClientData::parse(
+            &registration.client_data_json,
+            "webauthn.create",
+            &self.relying_party.origin,
+        )?;
+        let challenge = self
+            .take_challenge(tx, &client_data, REGISTRATION_CEREMONY)
+            .await?;
+        if challenge.and_then(|challenge| challenge.user_id) != Some(user_id) {
+            return Err(invalid_ceremony("Unknown or expired passkey challenge"));
+        }
+
+        let raw_auth_data = auth_data_from_attestation(&registration.attestation_object)?;
+        let auth_data = AuthenticatorData::parse(&raw_auth_data)?;
+        self.check_relying_party(&auth_data)?;
+        let (raw_credential_id, cose_key) = auth_data
+            .attested_credential
+            .ok_or_else(|| invalid_ceremony("Attestation has no credential data"))?;
+        let credential_id = URL_SAFE_NO_PAD.encode(raw_credential_id);
+        if credential_id != registration.credential_id {
+            return Err(invalid_ceremony(
+                "Credential id does not match the attestation",
+            ));
+        }
+        let public_key = verifying_key_from_cose(cose_key)?;
+
+        let maybe_credential = PasskeyCredentialData::find_by_credential_id(tx, &credential_id)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        if maybe_credential.is_some() {
+            return Err(AuthError::ConflictingAccount);
+        }
+
+        let random_auth_key = Uuid::new_v4();
+        let credential_record = PasskeyCredentialBeforeInsert {
+            credential_id: credential_id.clone(),
+            public_key: public_key.to_encoded_point(false).as_bytes().to_vec(),
+            auth_key: random_auth_key,
+            sign_count: i64::from(auth_data.sign_count),
+        };
+        let pair = UserAuthPairBeforeInsert {
+            auth_provider: PASSKEY_PROVIDER_NAME.to_owned(),
+            auth_key: random_auth_key.to_string(),
+            user_id,
+        };
+        PasskeyCredentialData::create(tx, credential_record)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let pair = UserAuthPairData::create(tx, pair)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        // The ceremony itself proves possession of the credential.
+        let pair = UserAuthPairData::update_is_verified(tx, &pair, true)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        audit_in(
+            &self.audit_log,
+            tx,
+            context,
+            AuditEntry::new(AuditEventKind::Registered, PASSKEY_PROVIDER_NAME)
+                .with_user(user_id)
+                .with_subject(&credential_id),
+        )
+        .await;
+        Ok(pair)
+    }
+
+    /// Passkeys have no out-of-band verification, so there is nothing to send.
+    async fn send_verify(
+        &self,
+        _account: &PasskeyAccount,
+        _verify_info: &VerifyInfo,
+    ) -> Result<(), AuthError> {
+        Ok(())
+    }
+
+    /// Passkeys have no verification codes, pairs are verified by the registration ceremony.
+    async fn check_verify_response(
+        &self,
+        _account: &PasskeyAccount,
+        _verify_code: &str,
+        _context: &AuthContext,
+    ) -> Result<bool, AuthError> {
+        Ok(false)
+    }
+
+    async fn try_unlink(&self, pair: &UserAuthPairData) -> Result<(), AuthError> {
+        let tx = self
+            .database_connection
+            .begin()
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        self.try_unlink_in(&tx, pair).await?;
+        tx.commit().await.map_err(AuthError::DatabaseError)
+    }
+
+    async fn try_unlink_in(
+        &self,
+        tx: &DatabaseTransaction,
+        pair: &UserAuthPairData,
+    ) -> Result<(), AuthError> {
+        let auth_key = match Uuid::parse_str(&pair.auth_key) {
+            Ok(auth_key) if pair.auth_provider == PASSKEY_PROVIDER_NAME => auth_key,
+            _ => return Err(AuthError::NotLinked),
+        };
+        let maybe_credential = PasskeyCredentialData::find_by_auth_key(tx, auth_key)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        if let Some(credential) = maybe_credential {
+            PasskeyCredentialData::delete(tx, credential)
+                .await
+                .map_err(AuthError::DatabaseError)?;
+        }
+        UserAuthPairData::delete(tx, pair.clone())
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+use super::{AuthContext, AuthError, AuthProvider, VerifyInfo};
+use crate::audit_log::{audit, audit_in, AuditEntry, AuditLog};
+use crate::registration::find_available_user;
+use crate::repository::{
+    AuditEventKind, PhoneProviderBeforeInsert, PhoneProviderData, UserAuthPairBeforeInsert,
+    UserAuthPairData,
+};
+pub use crate::sms_gateway::{MemorySmsGateway, SmsGateway, SmsMessage};
+use crate::verify_code::{VerifyCodePolicy, VerifyCodeStatus};
+use phonenumber::country;
+use phonenumber::Mode;
+use sea_orm::{DatabaseConnection, DatabaseTransaction, TransactionTrait};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// `auth_provider` in [UserAuthPairData]
+const PHONE_PROVIDER_NAME: &str = "phone_provider";
+
+/// Sign in with one-time codes sent by SMS.
+pub struct PhoneProvider {
+    database_connection: Arc<DatabaseConnection>,
+    template: SmsTemplateFunction,
+    gateway: Arc<dyn SmsGateway>,
+    default_region: Option<country::Id>,
+    verify_code_policy: VerifyCodePolicy,
+    audit_log: Option<Arc<AuditLog>>,
+}
+
+pub type SmsTemplateFunction = fn(&VerifyInfo, &PhoneAccount) -> String;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhoneAccount {
+    /// Any format accepted by libphonenumber, stored as E.164.
+    pub phone_number: String,
+    /// One-time code to log in with, unused for registration.
+    pub code: Option<String>,
+}
+
+impl PhoneProvider {
+    pub fn new(
+        database_connection: Arc<DatabaseConnection>,
+        template: SmsTemplateFunction,
+        gateway: Arc<dyn SmsGateway>,
+    ) -> Self {
+        Self {
+            database_connection,
+            template,
+            gateway,
+            default_region: None,
+            verify_code_policy: VerifyCodePolicy::default(),
+            audit_log: None,
+        }
+    }
+
+    /// Region assumed for numbers without a `+<country code>` prefix.
+    pub fn with_default_region(mut self, region: country::Id) -> Self {
+        self.default_region = Some(region);
+        self
+    }
+
+    /// Expiry and attempt limit of the codes sent by [AuthProvider::send_verify].
+    pub fn with_verify_code_policy(mut self, policy: VerifyCodePolicy) -> Self {
+        self.verify_code_policy = policy;
+        self
+    }
+
+    /// Record authentication events in `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Parse `phone_number` and format it as E.164, e.g. `+14155550123`.
+    pub fn normalize(&self, phone_number: &str) -> Result<String, AuthError> {
+        match phonenumber::parse(self.default_region, phone_number) {
+            Ok(parsed) if parsed.is_valid() => Ok(parsed.format().mode(Mode::E164).to_string()),
+            _ => Err(AuthError::InvalidPhoneNumber(phone_number.to_owned())),
+        }
+    }
+
+    /// Generate a code according to the policy and send it to `phone_number`.
+    pub async fn send_code(&self, phone_number: &str, service_name: &str) -> Result<(), AuthError> {
+        let verify_info = VerifyInfo {
+            verify_code: self.verify_code_policy.generate(),
+            service_name: service_name.to_owned(),
+            user_account_description: phone_number.to_owned(),
+        };
+        let account = PhoneAccount {
+            phone_number: phone_number.to_owned(),
+            code: None,
+        };
+        self.send_verify(&account, &verify_info).await
+    }
+
+    /// Check `code` against the one sent to `phone_number`, burning it when accepted.
+    ///
+    /// On success the pair is marked verified, since the code proves possession of the phone.
+    async fn consume_code(
+        &self,
+        phone_number: &str,
+        code: &str,
+    ) -> Result<Option<UserAuthPairData>, AuthError> {
+        let db = self.database_connection.as_ref();
+        let maybe_record = PhoneProviderData::find_by_phone_number(db, phone_number)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let Some(record) = maybe_record else {
+            return Ok(None);
+        };
+        let status = self.verify_code_policy.check(
+            record.verify_code.as_deref(),
+            record.code_sent_at,
+            record.verify_attempts,
+            code,
+            chrono::Utc::now().naive_utc(),
+        );
+        if !matches!(
+            status,
+            VerifyCodeStatus::Accepted | VerifyCodeStatus::Rejected
+        ) {
+            status.into_result()?;
+            return Ok(None);
+        }
+        // Counted before comparing, so parallel guesses can't exceed the limit.
+        let is_counted = PhoneProviderData::record_verify_attempt(
+            db,
+            &record,
+            self.verify_code_policy.max_attempts,
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        if !is_counted {
+            return Err(AuthError::CodeAttemptsExhausted);
+        }
+        if status == VerifyCodeStatus::Rejected {
+            return Ok(None);
+        }
+        let is_consumed = PhoneProviderData::consume_verify_code(db, &record, code)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        if !is_consumed {
+            return Ok(None);
+        }
+        let maybe_pair =
+            UserAuthPairData::find_by_key(db, PHONE_PROVIDER_NAME, &record.auth_key.to_string())
+                .await
+                .map_err(AuthError::DatabaseError)?;
+        match maybe_pair {
+            Some(pair) if !pair.is_verified => {
+                UserAuthPairData::update_is_verified(db, &pair, true)
+                    .await
+                    .map(Some)
+                    .map_err(AuthError::DatabaseError)
+            }
+            maybe_pair => Ok(maybe_pair),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthProvider<PhoneAccount> for PhoneProvider {
+    async fn try_login(
+        &self,
+        account: &PhoneAccount,
+        context: &AuthContext,
+    ) -> Result<Option<UserAuthPairData>, AuthError> {
+        let phone_number = self.normalize(&account.phone_number)?;
+        let Some(code) = &account.code else {
+            return Ok(None);
+        };
+        let maybe_pair = self.consume_code(&phone_number, code).await?;
+        if let Some(pair) = &maybe_pair {
+            find_available_user(self.database_connection.as_ref(), pair.user_id).await?;
+        }
+        let entry = match &maybe_pair {
+            Some(pair) => AuditEntry::new(AuditEventKind::LoginSucceeded, PHONE_PROVIDER_NAME)
+                .with_user(pair.user_id),
+            None => AuditEntry::new(AuditEventKind::LoginFailed, PHONE_PROVIDER_NAME),
+        };
+        audit(&self.audit_log, context, entry.with_subject(&phone_number)).await;
+        Ok(maybe_pair)
+    }
+
+    async fn try_register(
+        &self,
+        account: &PhoneAccount,
+        user_id: Uuid,
+        context: &AuthContext,
+    ) -> Result<UserAuthPairData, AuthError> {
+        let tx = self
+            .database_connection
+            .begin()
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let pair = self.try_register_in(&tx, account, user_id, context).await?;
+        tx.commit().await.map_err(AuthError::DatabaseError)?;
+        Ok(pair)
+    }
+
+    async fn try_register_in(
+        &self,
+        tx: &DatabaseTransaction,
+        account: &PhoneAccount,
+        user_id: Uuid,
+        context: &AuthContext,
+    ) -> Result<UserAuthPairData, AuthError> {
+        let phone_number = self.normalize(&account.phone_number)?;
+        let maybe_record = PhoneProviderData::find_by_phone_number(tx, &phone_number)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        if maybe_record.is_some() {
+            return Err(AuthError::ConflictingAccount);
+        }
+
+        let random_auth_key = Uuid::new_v4();
+        let provider_record = PhoneProviderBeforeInsert {
+            phone_number: phone_number.clone(),
+            auth_key: random_auth_key,
+        };
+        let pair = UserAuthPairBeforeInsert {
+            auth_provider: PHONE_PROVIDER_NAME.to_owned(),
+            auth_key: random_auth_key.to_string(),
+            user_id,
+        };
+        PhoneProviderData::create(tx, provider_record)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let pair = UserAuthPairData::create(tx, pair)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        audit_in(
+            &self.audit_log,
+            tx,
+            context,
+            AuditEntry::new(AuditEventKind::Registered, PHONE_PROVIDER_NAME)
+                .with_user(user_id)
+                .with_subject(&phone_number),
+        )
+        .await;
+        Ok(pair)
+    }
+
+    /// Store the code of `verify_info` and text it to the number.
+    ///
+    /// Nothing is sent to numbers which are not registered, so the gateway can't be used
+    /// to message arbitrary numbers.
+    async fn send_verify(
+        &self,
+        account: &PhoneAccount,
+        verify_info: &VerifyInfo,
+    ) -> Result<(), AuthError> {
+        let phone_number = self.normalize(&account.phone_number)?;
+        let maybe_record = PhoneProviderData::find_by_phone_number(
+            self.database_connection.as_ref(),
+            &phone_number,
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        let Some(record) = maybe_record else {
+            return Ok(());
+        };
+        PhoneProviderData::set_verify_code(
+            self.database_connection.as_ref(),
+            &record,
+            verify_info.verify_code.clone(),
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        let body = (self.template)(verify_info, account);
+        self.gateway
+            .send(SmsMessage {
+                to: phone_number,
+                body,
+            })
+            .await
+    }
+
+    async fn check_verify_response(
+        &self,
+        account: &PhoneAccount,
+        verify_code: &str,
+        context: &AuthContext,
+    ) -> Result<bool, AuthError> {
+        let phone_number = self.normalize(&account.phone_number)?;
+        let maybe_pair = self.consume_code(&phone_number, verify_code).await?;
+        let entry = match &maybe_pair {
+            Some(pair) => AuditEntry::new(AuditEventKind::Verified, PHONE_PROVIDER_NAME)
+                .with_user(pair.user_id),
+            None => AuditEntry::new(AuditEventKind::VerificationFailed, PHONE_PROVIDER_NAME),
+        };
+        audit(&self.audit_log, context, entry.with_subject(&phone_number)).await;
+        Ok(maybe_pair.is_some())
+    }
+
+    async fn try_unlink(&self, pair: &UserAuthPairData) -> Result<(), AuthError> {
+        let tx = self
+            .database_connection
+            .begin()
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        self.try_unlink_in(&tx, pair).await?;
+        tx.commit().await.map_err(AuthError::DatabaseError)
+    }
+
+    async fn try_unlink_in(
+        &self,
+        tx: &DatabaseTransaction,
+        pair: &UserAuthPairData,
+    ) -> Result<(), AuthError> {
+        let auth_key = match Uuid::parse_str(&pair.auth_key) {
+            Ok(auth_key) if pair.auth_provider == PHONE_PROVIDER_NAME => auth_key,
+            _ => return Err(AuthError::NotLinked),
+        };
+        let maybe_record = PhoneProviderData::find_by_auth_key(tx, auth_key)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        if let Some(record) = maybe_record {
+            PhoneProviderData::delete(tx, record)
+                .await
+                .map_err(AuthError::DatabaseError)?;
+        }
+        UserAuthPairData::delete(tx, pair.clone())
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        Ok(())
+    }
+}
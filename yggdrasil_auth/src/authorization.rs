@@ -0,0 +1,386 @@
+use crate::auth_provider::AuthError;
+use crate::repository::{
+    RoleBeforeInsert, RoleData, RolePermissionData, UserRoleBeforeInsert, UserRoleData,
+};
+use sea_orm::{DatabaseConnection, TransactionTrait};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// A permission held by a user through one of their roles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionGrant {
+    pub permission: String,
+    /// `None` when the role was assigned without a resource and applies everywhere.
+    pub resource: Option<String>,
+}
+
+impl PermissionGrant {
+    fn allows(&self, permission: &str, resource: Option<&str>) -> bool {
+        let resource_matches = match (&self.resource, resource) {
+            (None, _) => true,
+            (Some(granted), Some(requested)) => granted == requested,
+            (Some(_), None) => false,
+        };
+        resource_matches && permission_matches(&self.permission, permission)
+    }
+}
+
+/// Whether `pattern` covers `permission`, segment by segment.
+///
+/// A `*` segment matches exactly one segment, or every remaining segment when it is the
+/// last one, so `shop.*` covers `shop.production.write` and `*` covers everything.
+pub fn permission_matches(pattern: &str, permission: &str) -> bool {
+    let mut pattern_segments = pattern.split('.').peekable();
+    let mut permission_segments = permission.split('.');
+    while let Some(pattern_segment) = pattern_segments.next() {
+        if pattern_segment == "*" && pattern_segments.peek().is_none() {
+            return permission_segments.next().is_some();
+        }
+        match permission_segments.next() {
+            Some(segment) if pattern_segment == "*" || pattern_segment == segment => {}
+            _ => return false,
+        }
+    }
+    permission_segments.next().is_none()
+}
+
+struct CachedGrants {
+    grants: Arc<Vec<PermissionGrant>>,
+    loaded_at: Instant,
+}
+
+#[derive(Default)]
+struct GrantCache {
+    entries: HashMap<Uuid, CachedGrants>,
+    /// Bumped by every invalidation, so grants loaded before one aren't cached after it.
+    generation: u64,
+}
+
+/// Answers "may this user do X", caching the effective permissions of each user.
+///
+/// Changes made through the methods of the authorizer invalidate the cache right away,
+/// changes made directly through the repositories are picked up after `cache_ttl`.
+pub struct Authorizer {
+    database_connection: Arc<DatabaseConnection>,
+    cache_ttl: Duration,
+    cache: Mutex<GrantCache>,
+}
+
+impl Authorizer {
+    pub fn new(database_connection: Arc<DatabaseConnection>) -> Self {
+        Self {
+            database_connection,
+            cache_ttl: Duration::from_secs(60),
+            cache: Mutex::new(GrantCache::default()),
+        }
+    }
+
+    /// How long effective permissions are kept before being read again, zero disables caching.
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    /// Every permission `user_id` holds, through any of their roles.
+    pub async fn effective_permissions(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Arc<Vec<PermissionGrant>>, AuthError> {
+        let generation = {
+            let cache = self.cache.lock().unwrap();
+            if let Some(cached) = cache.entries.get(&user_id) {
+                if cached.loaded_at.elapsed() < self.cache_ttl {
+                    return Ok(cached.grants.clone());
+                }
+            }
+            cache.generation
+        };
+        let db = self.database_connection.as_ref();
+        let assignments = UserRoleData::find_by_user_id(db, user_id)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let role_ids = assignments
+            .iter()
+            .map(|assignment| assignment.role_id)
+            .collect();
+        let permissions = RolePermissionData::find_by_role_ids(db, role_ids)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let grants: Vec<PermissionGrant> = assignments
+            .iter()
+            .flat_map(|assignment| {
+                permissions
+                    .iter()
+                    .filter(move |permission| permission.role_id == assignment.role_id)
+                    .map(move |permission| PermissionGrant {
+                        permission: permission.permission.clone(),
+                        resource: assignment.resource.clone(),
+                    })
+            })
+            .collect();
+        let grants = Arc::new(grants);
+        let mut cache = self.cache.lock().unwrap();
+        // An invalidation during the load may have revoked some of the grants just read.
+        if !self.cache_ttl.is_zero() && cache.generation == generation {
+            cache.entries.insert(
+                user_id,
+                CachedGrants {
+                    grants: grants.clone(),
+                    loaded_at: Instant::now(),
+                },
+            );
+        }
+        Ok(grants)
+    }
+
+    /// Whether `user_id` holds `permission` without being limited to a resource.
+    pub async fn authorize(&self, user_id: Uuid, permission: &str) -> Result<bool, AuthError> {
+        let grants = self.effective_permissions(user_id).await?;
+        Ok(grants.iter().any(|grant| grant.allows(permission, None)))
+    }
+
+    /// Whether `user_id` holds `permission` on `resource`, either directly or unscoped.
+    pub async fn authorize_on(
+        &self,
+        user_id: Uuid,
+        permission: &str,
+        resource: &str,
+    ) -> Result<bool, AuthError> {
+        let grants = self.effective_permissions(user_id).await?;
+        Ok(grants
+            .iter()
+            .any(|grant| grant.allows(permission, Some(resource))))
+    }
+
+    /// Like [`authorize`](Authorizer::authorize), failing with [`AuthError::PermissionDenied`].
+    pub async fn require(&self, user_id: Uuid, permission: &str) -> Result<(), AuthError> {
+        match self.authorize(user_id, permission).await? {
+            true => Ok(()),
+            false => Err(AuthError::PermissionDenied(permission.to_owned())),
+        }
+    }
+
+    /// Like [`authorize_on`](Authorizer::authorize_on), failing with
+    /// [`AuthError::PermissionDenied`].
+    pub async fn require_on(
+        &self,
+        user_id: Uuid,
+        permission: &str,
+        resource: &str,
+    ) -> Result<(), AuthError> {
+        match self.authorize_on(user_id, permission, resource).await? {
+            true => Ok(()),
+            false => Err(AuthError::PermissionDenied(permission.to_owned())),
+        }
+    }
+
+    pub fn invalidate(&self, user_id: Uuid) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.entries.remove(&user_id);
+        cache.generation += 1;
+    }
+
+    pub fn invalidate_all(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.entries.clear();
+        cache.generation += 1;
+    }
+
+    async fn find_role(&self, name: &str) -> Result<RoleData, AuthError> {
+        RoleData::find_by_name(self.database_connection.as_ref(), name)
+            .await
+            .map_err(AuthError::DatabaseError)?
+            .ok_or_else(|| AuthError::RoleNotFound(name.to_owned()))
+    }
+
+    pub async fn create_role(&self, name: &str, description: &str) -> Result<RoleData, AuthError> {
+        RoleData::create(
+            self.database_connection.as_ref(),
+            RoleBeforeInsert {
+                name: name.to_owned(),
+                description: description.to_owned(),
+            },
+        )
+        .await
+        .map_err(AuthError::DatabaseError)
+    }
+
+    /// Delete the role together with its permissions and assignments.
+    pub async fn delete_role(&self, name: &str) -> Result<(), AuthError> {
+        let role = self.find_role(name).await?;
+        let tx = self
+            .database_connection
+            .begin()
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        UserRoleData::delete_by_role_id(&tx, role.id_number)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        RolePermissionData::delete_by_role_id(&tx, role.id_number)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        RoleData::delete(&tx, role)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        tx.commit().await.map_err(AuthError::DatabaseError)?;
+        self.invalidate_all();
+        Ok(())
+    }
+
+    /// Add `permission` to the role, granting it to every user holding the role.
+    pub async fn grant_permission(&self, role: &str, permission: &str) -> Result<(), AuthError> {
+        let role = self.find_role(role).await?;
+        let db = self.database_connection.as_ref();
+        let existing = RolePermissionData::find_by_role_id(db, role.id_number)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        if !existing
+            .iter()
+            .any(|granted| granted.permission == permission)
+        {
+            RolePermissionData::create(db, role.id_number, permission)
+                .await
+                .map_err(AuthError::DatabaseError)?;
+        }
+        self.invalidate_all();
+        Ok(())
+    }
+
+    pub async fn revoke_permission(&self, role: &str, permission: &str) -> Result<(), AuthError> {
+        let role = self.find_role(role).await?;
+        RolePermissionData::delete_by_role_and_permission(
+            self.database_connection.as_ref(),
+            role.id_number,
+            permission,
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        self.invalidate_all();
+        Ok(())
+    }
+
+    /// Give `user_id` the role, limited to `resource` when set.
+    pub async fn assign_role(
+        &self,
+        user_id: Uuid,
+        role: &str,
+        resource: Option<&str>,
+    ) -> Result<UserRoleData, AuthError> {
+        let role = self.find_role(role).await?;
+        let db = self.database_connection.as_ref();
+        let existing = UserRoleData::find_assignment(db, user_id, role.id_number, resource)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        let assignment = match existing {
+            Some(assignment) => assignment,
+            None => UserRoleData::create(
+                db,
+                UserRoleBeforeInsert {
+                    user_id,
+                    role_id: role.id_number,
+                    resource: resource.map(str::to_owned),
+                },
+            )
+            .await
+            .map_err(AuthError::DatabaseError)?,
+        };
+        self.invalidate(user_id);
+        Ok(assignment)
+    }
+
+    pub async fn unassign_role(
+        &self,
+        user_id: Uuid,
+        role: &str,
+        resource: Option<&str>,
+    ) -> Result<(), AuthError> {
+        let role = self.find_role(role).await?;
+        let db = self.database_connection.as_ref();
+        let existing = UserRoleData::find_assignment(db, user_id, role.id_number, resource)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        if let Some(assignment) = existing {
+            UserRoleData::delete(db, assignment)
+                .await
+                .map_err(AuthError::DatabaseError)?;
+        }
+        self.invalidate(user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_permission_matches_only_itself() {
+        assert!(permission_matches(
+            "shop.production.write",
+            "shop.production.write"
+        ));
+        assert!(!permission_matches(
+            "shop.production.write",
+            "shop.production.read"
+        ));
+        assert!(!permission_matches(
+            "shop.production",
+            "shop.production.write"
+        ));
+        assert!(!permission_matches(
+            "shop.production.write",
+            "shop.production"
+        ));
+    }
+
+    #[test]
+    fn trailing_wildcard_matches_every_remaining_segment() {
+        assert!(permission_matches("*", "shop"));
+        assert!(permission_matches("*", "shop.production.write"));
+        assert!(permission_matches("shop.*", "shop.order"));
+        assert!(permission_matches("shop.*", "shop.production.write"));
+        assert!(!permission_matches("shop.*", "shop"));
+        assert!(!permission_matches("shop.*", "affiliate.withdrawal.review"));
+    }
+
+    #[test]
+    fn inner_wildcard_matches_exactly_one_segment() {
+        assert!(permission_matches("shop.*.write", "shop.production.write"));
+        assert!(permission_matches("shop.*.write", "shop.order.write"));
+        assert!(!permission_matches("shop.*.write", "shop.production.read"));
+        assert!(!permission_matches("shop.*.write", "shop.write"));
+        assert!(!permission_matches(
+            "shop.*.write",
+            "shop.production.write.all"
+        ));
+    }
+
+    #[test]
+    fn wildcard_is_only_special_as_a_whole_segment() {
+        assert!(!permission_matches("shop.prod*", "shop.production"));
+        assert!(!permission_matches("shop*", "shop.production"));
+    }
+
+    #[test]
+    fn unscoped_grant_allows_every_resource() {
+        let grant = PermissionGrant {
+            permission: "shop.*".to_owned(),
+            resource: None,
+        };
+        assert!(grant.allows("shop.order.refund", None));
+        assert!(grant.allows("shop.order.refund", Some("store-1")));
+    }
+
+    #[test]
+    fn scoped_grant_allows_its_resource_only() {
+        let grant = PermissionGrant {
+            permission: "shop.*".to_owned(),
+            resource: Some("store-1".to_owned()),
+        };
+        assert!(grant.allows("shop.order.refund", Some("store-1")));
+        assert!(!grant.allows("shop.order.refund", Some("store-2")));
+        assert!(!grant.allows("shop.order.refund", None));
+    }
+}
@@ -1,3 +1,11 @@
+pub mod account_link;
+pub mod audit_log;
 pub mod auth_provider;
+pub mod authorization;
+pub mod login_throttle;
+pub mod mailer;
 pub mod password_hash;
+pub mod registration;
 pub mod repository;
+pub mod sms_gateway;
+pub mod verify_code;
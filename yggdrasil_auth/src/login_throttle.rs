@@ -0,0 +1,320 @@
+use crate::auth_provider::AuthError;
+use crate::repository::LoginAttemptData;
+use chrono::{Duration, NaiveDateTime};
+use sea_orm::DatabaseConnection;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+/// Failed login attempts of one throttled subject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginAttemptRecord {
+    pub failures: i32,
+    pub window_started_at: NaiveDateTime,
+    pub last_failure_at: NaiveDateTime,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+#[async_trait::async_trait]
+pub trait LoginAttemptStore: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<LoginAttemptRecord>, AuthError>;
+    async fn put(&self, key: &str, record: LoginAttemptRecord) -> Result<(), AuthError>;
+    /// Count a failure at `now` under `rule` and return the updated record.
+    ///
+    /// Must be atomic, so that concurrent failures are all counted.
+    async fn increment(
+        &self,
+        key: &str,
+        now: NaiveDateTime,
+        rule: &ThrottleRule,
+    ) -> Result<LoginAttemptRecord, AuthError>;
+    async fn clear(&self, key: &str) -> Result<(), AuthError>;
+}
+
+/// Keeps attempts in process memory, suitable for a single instance deployment.
+#[derive(Default)]
+pub struct MemoryLoginAttemptStore {
+    records: Mutex<HashMap<String, LoginAttemptRecord>>,
+}
+
+impl MemoryLoginAttemptStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for MemoryLoginAttemptStore {
+    async fn get(&self, key: &str) -> Result<Option<LoginAttemptRecord>, AuthError> {
+        Ok(self.records.lock().unwrap().get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, record: LoginAttemptRecord) -> Result<(), AuthError> {
+        self.records.lock().unwrap().insert(key.to_owned(), record);
+        Ok(())
+    }
+
+    async fn increment(
+        &self,
+        key: &str,
+        now: NaiveDateTime,
+        rule: &ThrottleRule,
+    ) -> Result<LoginAttemptRecord, AuthError> {
+        let mut records = self.records.lock().unwrap();
+        let previous = records
+            .get(key)
+            .filter(|record| !rule.is_expired(record, now));
+        let mut record = previous.cloned().unwrap_or(LoginAttemptRecord {
+            failures: 0,
+            window_started_at: now,
+            last_failure_at: now,
+            locked_until: None,
+        });
+        record.failures += 1;
+        record.last_failure_at = now;
+        if let Some(threshold) = rule.lockout_threshold {
+            if record.failures >= threshold && record.locked_until.is_none() {
+                record.locked_until = Some(now + rule.lockout_duration);
+            }
+        }
+        records.insert(key.to_owned(), record.clone());
+        Ok(record)
+    }
+
+    async fn clear(&self, key: &str) -> Result<(), AuthError> {
+        self.records.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// Keeps attempts in `ygg_auth__login_attempt`, shared by every instance.
+pub struct DatabaseLoginAttemptStore {
+    database_connection: Arc<DatabaseConnection>,
+}
+
+impl DatabaseLoginAttemptStore {
+    pub fn new(database_connection: Arc<DatabaseConnection>) -> Self {
+        Self {
+            database_connection,
+        }
+    }
+
+    /// Remove records which have not seen a failure since `before`.
+    pub async fn prune(&self, before: NaiveDateTime) -> Result<u64, AuthError> {
+        LoginAttemptData::delete_stale(self.database_connection.as_ref(), before)
+            .await
+            .map(|result| result.rows_affected)
+            .map_err(AuthError::DatabaseError)
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for DatabaseLoginAttemptStore {
+    async fn get(&self, key: &str) -> Result<Option<LoginAttemptRecord>, AuthError> {
+        let maybe_record = LoginAttemptData::find_by_key(self.database_connection.as_ref(), key)
+            .await
+            .map_err(AuthError::DatabaseError)?;
+        Ok(maybe_record.map(|record| LoginAttemptRecord {
+            failures: record.failures,
+            window_started_at: record.window_started_at,
+            last_failure_at: record.last_failure_at,
+            locked_until: record.locked_until,
+        }))
+    }
+
+    async fn put(&self, key: &str, record: LoginAttemptRecord) -> Result<(), AuthError> {
+        LoginAttemptData::save(
+            self.database_connection.as_ref(),
+            LoginAttemptData {
+                throttle_key: key.to_owned(),
+                failures: record.failures,
+                window_started_at: record.window_started_at,
+                last_failure_at: record.last_failure_at,
+                locked_until: record.locked_until,
+            },
+        )
+        .await
+        .map_err(AuthError::DatabaseError)
+    }
+
+    async fn increment(
+        &self,
+        key: &str,
+        now: NaiveDateTime,
+        rule: &ThrottleRule,
+    ) -> Result<LoginAttemptRecord, AuthError> {
+        let record = LoginAttemptData::increment(
+            self.database_connection.as_ref(),
+            key,
+            now,
+            now - rule.window,
+            rule.lockout_threshold,
+            now + rule.lockout_duration,
+        )
+        .await
+        .map_err(AuthError::DatabaseError)?;
+        Ok(LoginAttemptRecord {
+            failures: record.failures,
+            window_started_at: record.window_started_at,
+            last_failure_at: record.last_failure_at,
+            locked_until: record.locked_until,
+        })
+    }
+
+    async fn clear(&self, key: &str) -> Result<(), AuthError> {
+        LoginAttemptData::delete_by_key(self.database_connection.as_ref(), key)
+            .await
+            .map(|_| ())
+            .map_err(AuthError::DatabaseError)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThrottleRule {
+    /// Failures tolerated before any delay is imposed.
+    pub free_attempts: i32,
+    /// Delay after the first throttled failure, doubled by every further failure.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Failures within `window` which lock the subject out, `None` to only delay.
+    pub lockout_threshold: Option<i32>,
+    pub lockout_duration: Duration,
+    /// Failures older than this are forgotten.
+    pub window: Duration,
+}
+
+impl ThrottleRule {
+    fn delay_after(&self, failures: i32) -> Duration {
+        if failures <= self.free_attempts {
+            return Duration::zero();
+        }
+        let exponent = (failures - self.free_attempts - 1).min(20) as u32;
+        (self.base_delay * 2_i32.pow(exponent)).min(self.max_delay)
+    }
+
+    /// Whether `record` no longer counts, so the next failure starts over.
+    fn is_expired(&self, record: &LoginAttemptRecord, now: NaiveDateTime) -> bool {
+        record.window_started_at + self.window < now
+            || record.locked_until.is_some_and(|until| until <= now)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ThrottleScope {
+    Email,
+    Ip,
+    Global,
+}
+
+/// Rate limits password guesses per email, per client ip and across the whole service.
+pub struct LoginThrottle {
+    store: Arc<dyn LoginAttemptStore>,
+    per_email: Option<ThrottleRule>,
+    per_ip: Option<ThrottleRule>,
+    global: Option<ThrottleRule>,
+}
+
+impl LoginThrottle {
+    pub fn new(store: Arc<dyn LoginAttemptStore>) -> Self {
+        Self {
+            store,
+            per_email: None,
+            per_ip: None,
+            global: None,
+        }
+    }
+
+    pub fn per_email(mut self, rule: ThrottleRule) -> Self {
+        self.per_email = Some(rule);
+        self
+    }
+
+    pub fn per_ip(mut self, rule: ThrottleRule) -> Self {
+        self.per_ip = Some(rule);
+        self
+    }
+
+    pub fn global(mut self, rule: ThrottleRule) -> Self {
+        self.global = Some(rule);
+        self
+    }
+
+    fn subjects(
+        &self,
+        email: &str,
+        ip: Option<IpAddr>,
+    ) -> Vec<(ThrottleScope, String, &ThrottleRule)> {
+        let mut subjects = Vec::with_capacity(3);
+        if let Some(rule) = &self.per_email {
+            subjects.push((ThrottleScope::Email, email_key(email), rule));
+        }
+        if let (Some(rule), Some(ip)) = (&self.per_ip, ip) {
+            subjects.push((ThrottleScope::Ip, format!("ip:{}", ip), rule));
+        }
+        if let Some(rule) = &self.global {
+            subjects.push((ThrottleScope::Global, "global".to_owned(), rule));
+        }
+        subjects
+    }
+
+    /// Fail if a login attempt for `email` from `ip` is not allowed right now.
+    pub async fn check(&self, email: &str, ip: Option<IpAddr>) -> Result<(), AuthError> {
+        let now = chrono::Utc::now().naive_utc();
+        for (scope, key, rule) in self.subjects(email, ip) {
+            let Some(record) = self.store.get(&key).await? else {
+                continue;
+            };
+            if let Some(locked_until) = record.locked_until.filter(|until| *until > now) {
+                return Err(match scope {
+                    ThrottleScope::Email => AuthError::AccountLocked {
+                        until: locked_until,
+                    },
+                    _ => AuthError::LoginThrottled {
+                        retry_at: locked_until,
+                    },
+                });
+            }
+            if record.window_started_at + rule.window < now {
+                continue;
+            }
+            let retry_at = record.last_failure_at + rule.delay_after(record.failures);
+            if retry_at > now {
+                return Err(AuthError::LoginThrottled { retry_at });
+            }
+        }
+        Ok(())
+    }
+
+    /// Count a failed attempt against every configured scope.
+    ///
+    /// Returns the end of the lockout when this failure locked `email` out.
+    pub async fn record_failure(
+        &self,
+        email: &str,
+        ip: Option<IpAddr>,
+    ) -> Result<Option<NaiveDateTime>, AuthError> {
+        let now = chrono::Utc::now().naive_utc();
+        let mut email_locked_until = None;
+        for (scope, key, rule) in self.subjects(email, ip) {
+            let record = self.store.increment(&key, now, rule).await?;
+            // Only the failure reaching the threshold locks, later ones see the lock set.
+            let just_locked = rule.lockout_threshold == Some(record.failures);
+            if scope == ThrottleScope::Email && just_locked {
+                email_locked_until = record.locked_until;
+            }
+        }
+        Ok(email_locked_until)
+    }
+
+    /// Forget the failures of `email`, lifting its lockout.
+    ///
+    /// Ip and global counters are left alone so a successful login to one account
+    /// doesn't reset the budget of someone guessing passwords of others.
+    pub async fn clear_email(&self, email: &str) -> Result<(), AuthError> {
+        self.store.clear(&email_key(email)).await
+    }
+}
+
+fn email_key(email: &str) -> String {
+    format!("email:{}", email.to_lowercase())
+}
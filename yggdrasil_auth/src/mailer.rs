@@ -0,0 +1,141 @@
+use crate::auth_provider::AuthError;
+use lettre::message::header::ContentType;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailContent {
+    pub subject: String,
+    pub from: String,
+    pub to: String,
+    pub content: String,
+    pub content_type: ContentType,
+}
+
+impl EmailContent {
+    pub fn to_message(&self) -> Result<Message, AuthError> {
+        Message::builder()
+            .from(
+                self.from
+                    .parse()
+                    .map_err(|_| AuthError::InvalidAddress(self.from.clone()))?,
+            )
+            .to(self
+                .to
+                .parse()
+                .map_err(|_| AuthError::InvalidAddress(self.to.clone()))?)
+            .subject(self.subject.clone())
+            .header(self.content_type.clone())
+            .body(self.content.clone())
+            .map_err(|err| AuthError::delivery_failed("Failed to build email", err))
+    }
+}
+
+#[async_trait::async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: EmailContent) -> Result<(), AuthError>;
+}
+
+/// Delivers through an SMTP relay without blocking the runtime.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(transport: AsyncSmtpTransport<Tokio1Executor>) -> Self {
+        Self { transport }
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: EmailContent) -> Result<(), AuthError> {
+        let message = email.to_message()?;
+        match self.transport.send(message).await {
+            Ok(_) => Ok(()),
+            Err(err) => Err(AuthError::delivery_failed("Failed to send email", err)),
+        }
+    }
+}
+
+/// Writes every email as an `.eml` file, or to stdout, for local development.
+pub struct FileMailer {
+    directory: Option<PathBuf>,
+}
+
+impl FileMailer {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: Some(directory.into()),
+        }
+    }
+
+    pub fn stdout() -> Self {
+        Self { directory: None }
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: EmailContent) -> Result<(), AuthError> {
+        let formatted = email.to_message()?.formatted();
+        let written = match &self.directory {
+            Some(directory) => {
+                let path = directory.join(format!("{}.eml", uuid::Uuid::new_v4()));
+                tokio::fs::write(path, formatted).await
+            }
+            None => {
+                let mut stdout = tokio::io::stdout();
+                match stdout.write_all(&formatted).await {
+                    Ok(_) => stdout.write_all(b"\n").await,
+                    Err(err) => Err(err),
+                }
+            }
+        };
+        written.map_err(|err| AuthError::delivery_failed("Failed to write email", err))
+    }
+}
+
+/// Keeps sent emails in memory so tests can assert on them.
+#[derive(Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<EmailContent>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every email sent so far, oldest first.
+    pub fn sent(&self) -> Vec<EmailContent> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    /// The most recent email sent to `to`.
+    pub fn last_sent_to(&self, to: &str) -> Option<EmailContent> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|email| email.to == to)
+            .cloned()
+    }
+
+    pub fn clear(&self) {
+        self.sent.lock().unwrap().clear();
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, email: EmailContent) -> Result<(), AuthError> {
+        // Building the message validates the addresses like a real transport would.
+        email.to_message()?;
+        self.sent.lock().unwrap().push(email);
+        Ok(())
+    }
+}
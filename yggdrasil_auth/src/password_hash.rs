@@ -1,66 +1,235 @@
-use crate::auth_provider::AuthError;
 use argon2::password_hash::rand_core::OsRng;
-use argon2::password_hash::SaltString;
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use argon2::password_hash::{PasswordHasher as _, PasswordVerifier as _, SaltString};
+use argon2::{Algorithm, Argon2, PasswordHash, Version};
+use scrypt::Scrypt;
+use std::str::FromStr;
+use std::sync::Arc;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+pub use argon2::Params as Argon2Params;
+pub use scrypt::Params as ScryptParams;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum HashError {
+    #[error("failed to hash password")]
     IntoHashError,
+    #[error("stored password hash is malformed or uses an unknown algorithm")]
     FromHashError,
 }
 
-impl Into<AuthError> for HashError {
-    fn into(self) -> AuthError {
-        match self {
-            HashError::IntoHashError => {
-                AuthError::VerifyAlgorithmError("Failed to hash password".to_owned())
+pub trait PasswordHasher: Send + Sync {
+    /// Hash `password` with the configured parameters.
+    fn hash(&self, password: &str) -> Result<String, HashError>;
+
+    /// Check `password` against a `hash` this hasher [recognizes](PasswordHasher::recognizes).
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, HashError>;
+
+    /// Whether `hash` was produced by this algorithm, with any parameters.
+    fn recognizes(&self, hash: &str) -> bool;
+
+    /// Whether `hash` should be replaced by one using the configured algorithm and parameters.
+    fn needs_rehash(&self, hash: &str) -> bool;
+}
+
+/// Argon2id producing PHC strings such as `$argon2id$v=19$m=19456,t=2,p=1$...`.
+#[derive(Debug, Clone, Default)]
+pub struct Argon2idHasher {
+    params: Argon2Params,
+}
+
+impl Argon2idHasher {
+    pub fn new(params: Argon2Params) -> Self {
+        Self { params }
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+}
+
+impl PasswordHasher for Argon2idHasher {
+    fn hash(&self, password: &str) -> Result<String, HashError> {
+        let salt = SaltString::generate(&mut OsRng);
+        match self.argon2().hash_password(password.as_bytes(), &salt) {
+            Ok(hash) => Ok(hash.to_string()),
+            Err(_) => Err(HashError::IntoHashError),
+        }
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, HashError> {
+        let parsed_hash = PasswordHash::new(hash).map_err(|_| HashError::FromHashError)?;
+        // Algorithm, version and parameters are taken from the parsed hash.
+        match Argon2::default().verify_password(password.as_bytes(), &parsed_hash) {
+            Ok(_) => Ok(true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$argon2")
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed_hash) = PasswordHash::new(hash) else {
+            return true;
+        };
+        if parsed_hash.algorithm != Algorithm::Argon2id.ident()
+            || parsed_hash.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+        match Argon2Params::try_from(&parsed_hash) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
             }
-            HashError::FromHashError => AuthError::VerifyAlgorithmError(
-                "Failed to verify password because of hash algorithm error.".to_owned(),
-            ),
+            Err(_) => true,
         }
     }
 }
 
-pub type IntoHashedFunction = fn(&str) -> Result<String, HashError>;
-pub type VerifyHashedFunction = fn(&str, &str) -> Result<bool, HashError>;
+/// Bcrypt producing modular crypt strings such as `$2b$12$...`.
+#[derive(Debug, Clone)]
+pub struct BcryptHasher {
+    cost: u32,
+}
+
+impl BcryptHasher {
+    pub fn new(cost: u32) -> Self {
+        Self { cost }
+    }
+}
 
-pub struct HashFunction {
-    pub into_hashed: IntoHashedFunction,
-    pub verify: VerifyHashedFunction,
+impl Default for BcryptHasher {
+    fn default() -> Self {
+        Self::new(bcrypt::DEFAULT_COST)
+    }
 }
 
-pub static BCRYPT_HASH_FUNCTION: HashFunction = HashFunction {
-    into_hashed: hash_password_bcrypt,
-    verify: verify_password_bcrypt,
-};
+impl PasswordHasher for BcryptHasher {
+    fn hash(&self, password: &str) -> Result<String, HashError> {
+        bcrypt::hash(password, self.cost).map_err(|_| HashError::IntoHashError)
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, HashError> {
+        bcrypt::verify(password, hash).map_err(|_| HashError::FromHashError)
+    }
 
-pub static ARGON2_HASH_FUNCTION: HashFunction = HashFunction {
-    into_hashed: hash_password_argon2,
-    verify: verify_password_argon2,
-};
+    fn recognizes(&self, hash: &str) -> bool {
+        ["$2a$", "$2b$", "$2x$", "$2y$"]
+            .iter()
+            .any(|prefix| hash.starts_with(prefix))
+    }
 
-fn hash_password_bcrypt(password: &str) -> Result<String, HashError> {
-    bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(|_| HashError::IntoHashError)
+    fn needs_rehash(&self, hash: &str) -> bool {
+        match bcrypt::HashParts::from_str(hash) {
+            Ok(parts) => parts.get_cost() != self.cost,
+            Err(_) => true,
+        }
+    }
 }
 
-fn verify_password_bcrypt(password: &str, hash: &str) -> Result<bool, HashError> {
-    bcrypt::verify(password, hash).map_err(|_| HashError::FromHashError)
+/// Scrypt producing PHC strings such as `$scrypt$ln=17,r=8,p=1$...`.
+#[derive(Debug, Clone, Default)]
+pub struct ScryptHasher {
+    params: ScryptParams,
 }
 
-fn hash_password_argon2(password: &str) -> Result<String, HashError> {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    match argon2.hash_password(password.as_bytes(), &salt) {
-        Ok(hash) => Ok(hash.to_string()),
-        Err(_) => Err(HashError::IntoHashError),
+impl ScryptHasher {
+    pub fn new(params: ScryptParams) -> Self {
+        Self { params }
     }
 }
 
-fn verify_password_argon2(password: &str, hash: &str) -> Result<bool, HashError> {
-    let parsed_hash = PasswordHash::new(&hash).map_err(|_| HashError::FromHashError)?;
-    match Argon2::default().verify_password(password.as_bytes(), &parsed_hash) {
-        Ok(_) => Ok(true),
-        Err(_) => Ok(false),
+impl PasswordHasher for ScryptHasher {
+    fn hash(&self, password: &str) -> Result<String, HashError> {
+        let salt = SaltString::generate(&mut OsRng);
+        match Scrypt.hash_password_customized(password.as_bytes(), None, None, self.params, &salt) {
+            Ok(hash) => Ok(hash.to_string()),
+            Err(_) => Err(HashError::IntoHashError),
+        }
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, HashError> {
+        let parsed_hash = PasswordHash::new(hash).map_err(|_| HashError::FromHashError)?;
+        match Scrypt.verify_password(password.as_bytes(), &parsed_hash) {
+            Ok(_) => Ok(true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$scrypt$")
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed_hash) = PasswordHash::new(hash) else {
+            return true;
+        };
+        match ScryptParams::try_from(&parsed_hash) {
+            Ok(params) => {
+                params.log_n() != self.params.log_n()
+                    || params.r() != self.params.r()
+                    || params.p() != self.params.p()
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+/// Hashes with `current` and verifies with whichever hasher recognizes the stored string,
+/// so stored hashes can be migrated to new algorithms or parameters on the next login.
+pub struct MultiPasswordHasher {
+    current: Arc<dyn PasswordHasher>,
+    legacy: Vec<Arc<dyn PasswordHasher>>,
+}
+
+impl MultiPasswordHasher {
+    pub fn new(current: Arc<dyn PasswordHasher>) -> Self {
+        Self {
+            current,
+            legacy: Vec::new(),
+        }
+    }
+
+    /// Hash with `current`, and still accept every algorithm supported by this crate.
+    pub fn with_all_algorithms(current: Arc<dyn PasswordHasher>) -> Self {
+        Self::new(current)
+            .with_legacy(Arc::new(Argon2idHasher::default()))
+            .with_legacy(Arc::new(BcryptHasher::default()))
+            .with_legacy(Arc::new(ScryptHasher::default()))
+    }
+
+    /// Accept hashes recognized by `hasher`, replacing them on the next successful login.
+    pub fn with_legacy(mut self, hasher: Arc<dyn PasswordHasher>) -> Self {
+        self.legacy.push(hasher);
+        self
+    }
+
+    fn detect(&self, hash: &str) -> Option<&Arc<dyn PasswordHasher>> {
+        std::iter::once(&self.current)
+            .chain(self.legacy.iter())
+            .find(|hasher| hasher.recognizes(hash))
+    }
+}
+
+impl PasswordHasher for MultiPasswordHasher {
+    fn hash(&self, password: &str) -> Result<String, HashError> {
+        self.current.hash(password)
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, HashError> {
+        match self.detect(hash) {
+            Some(hasher) => hasher.verify(password, hash),
+            None => Err(HashError::FromHashError),
+        }
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        self.detect(hash).is_some()
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        !self.current.recognizes(hash) || self.current.needs_rehash(hash)
     }
 }
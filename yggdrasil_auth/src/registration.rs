@@ -0,0 +1,100 @@
+use crate::auth_provider::api_key_provider::{ApiKeyAccount, ApiKeyProvider, NewApiKey};
+use crate::auth_provider::{AuthContext, AuthError, AuthProvider};
+use crate::repository::UserAuthPairData;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DatabaseTransaction, TransactionTrait};
+use uuid::Uuid;
+use yggdrasil_user::repository::{UserBeforeInsert, UserData};
+
+/// Create a user together with their first login method, or neither of them.
+pub async fn register_user<Account, Provider>(
+    db: &DatabaseConnection,
+    provider: &Provider,
+    account: &Account,
+    user: UserBeforeInsert,
+    context: &AuthContext,
+) -> Result<(UserData, UserAuthPairData), AuthError>
+where
+    Account: Send + Sync + Sized + Clone,
+    Provider: AuthProvider<Account>,
+{
+    let tx = db.begin().await.map_err(AuthError::DatabaseError)?;
+    let registered = register_user_in(&tx, provider, account, user, context).await?;
+    tx.commit().await.map_err(AuthError::DatabaseError)?;
+    Ok(registered)
+}
+
+/// Same as [register_user], but inside the caller's transaction so other records, e.g. an
+/// event announcing the registration, are created together with the user.
+pub async fn register_user_in<Account, Provider>(
+    tx: &DatabaseTransaction,
+    provider: &Provider,
+    account: &Account,
+    user: UserBeforeInsert,
+    context: &AuthContext,
+) -> Result<(UserData, UserAuthPairData), AuthError>
+where
+    Account: Send + Sync + Sized + Clone,
+    Provider: AuthProvider<Account>,
+{
+    let user = UserData::create(tx, user)
+        .await
+        .map_err(AuthError::DatabaseError)?;
+    let pair = provider
+        .try_register_in(tx, account, user.id, context)
+        .await?;
+    Ok((user, pair))
+}
+
+/// Create a service account which signs in with `key` only.
+pub async fn register_service_account(
+    db: &DatabaseConnection,
+    provider: &ApiKeyProvider,
+    display_name: &str,
+    key: NewApiKey,
+    context: &AuthContext,
+) -> Result<(UserData, UserAuthPairData), AuthError> {
+    let user = UserBeforeInsert {
+        display_name: display_name.to_owned(),
+        is_service_account: true,
+    };
+    register_user(db, provider, &ApiKeyAccount::New(key), user, context).await
+}
+
+/// The user a login belongs to, failing with [AuthError::UserUnavailable] if they are
+/// suspended or deleted. Every way of logging in checks this before handing out a pair.
+pub async fn find_available_user(
+    db: &impl ConnectionTrait,
+    user_id: Uuid,
+) -> Result<UserData, AuthError> {
+    let maybe_user = UserData::find_by_id_with_deleted(db, user_id)
+        .await
+        .map_err(AuthError::DatabaseError)?;
+    match maybe_user {
+        Some(user) if user.is_active() => Ok(user),
+        Some(_) => Err(AuthError::UserUnavailable),
+        None => Err(AuthError::UserNotFound),
+    }
+}
+
+/// Log in with `account` and record the time of the login on the user.
+///
+/// Users who are suspended or deleted are rejected even if the credentials are correct.
+pub async fn sign_in<Account, Provider>(
+    db: &DatabaseConnection,
+    provider: &Provider,
+    account: &Account,
+    context: &AuthContext,
+) -> Result<Option<(UserData, UserAuthPairData)>, AuthError>
+where
+    Account: Send + Sync + Sized + Clone,
+    Provider: AuthProvider<Account>,
+{
+    let Some(pair) = provider.try_login(account, context).await? else {
+        return Ok(None);
+    };
+    let user = find_available_user(db, pair.user_id).await?;
+    let user = UserData::touch_last_login(db, &user)
+        .await
+        .map_err(AuthError::DatabaseError)?;
+    Ok(Some((user, pair)))
+}
@@ -0,0 +1,137 @@
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter,
+    PrimaryKeyTrait, QueryFilter, QueryOrder,
+};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_auth__api_key")]
+pub struct Model {
+    /// Public part of the key, shown in listings so users can tell their keys apart.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub key_id: String,
+
+    /// Hex encoded SHA-256 of the secret part of the key.
+    pub key_hash: String,
+
+    #[sea_orm(index, unique)]
+    pub auth_key: Uuid,
+
+    #[sea_orm(index)]
+    pub user_id: Uuid,
+
+    pub name: String,
+
+    /// Space separated scopes such as `shop.production.read`.
+    pub scopes: String,
+
+    pub created_at: chrono::NaiveDateTime,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    pub last_used_at: Option<chrono::NaiveDateTime>,
+    pub revoked_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type ApiKeyData = Model;
+pub type ApiKeyEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiKeyBeforeInsert {
+    pub key_id: String,
+    pub key_hash: String,
+    pub auth_key: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub scopes: String,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+}
+
+impl ApiKeyData {
+    pub fn scope_list(&self) -> Vec<&str> {
+        self.scopes.split_whitespace().collect()
+    }
+
+    /// Whether the key is neither revoked nor expired at `now`.
+    pub fn is_usable_at(&self, now: chrono::NaiveDateTime) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: ApiKeyBeforeInsert,
+    ) -> Result<ApiKeyData, DbErr> {
+        ActiveModel {
+            key_id: Set(data.key_id),
+            key_hash: Set(data.key_hash),
+            auth_key: Set(data.auth_key),
+            user_id: Set(data.user_id),
+            name: Set(data.name),
+            scopes: Set(data.scopes),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            expires_at: Set(data.expires_at),
+            last_used_at: Set(None),
+            revoked_at: Set(None),
+        }
+        .insert(db)
+        .await
+    }
+
+    pub async fn find_by_key_id(
+        db: &impl ConnectionTrait,
+        key_id: &str,
+    ) -> Result<Option<ApiKeyData>, DbErr> {
+        Entity::find_by_id(key_id.to_owned()).one(db).await
+    }
+
+    pub async fn find_by_auth_key(
+        db: &impl ConnectionTrait,
+        auth_key: Uuid,
+    ) -> Result<Option<ApiKeyData>, DbErr> {
+        Entity::find()
+            .filter(Column::AuthKey.eq(auth_key))
+            .one(db)
+            .await
+    }
+
+    pub async fn find_by_user_id(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+    ) -> Result<Vec<ApiKeyData>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_asc(Column::CreatedAt)
+            .all(db)
+            .await
+    }
+
+    pub async fn touch_last_used(
+        db: &impl ConnectionTrait,
+        before: &ApiKeyData,
+    ) -> Result<ApiKeyData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.last_used_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active.update(db).await
+    }
+
+    pub async fn revoke(
+        db: &impl ConnectionTrait,
+        before: &ApiKeyData,
+    ) -> Result<ApiKeyData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.revoked_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active.update(db).await
+    }
+
+    pub async fn delete(
+        db: &impl ConnectionTrait,
+        before: ApiKeyData,
+    ) -> Result<DeleteResult, DbErr> {
+        let active: ActiveModel = before.into();
+        Entity::delete(active).exec(db).await
+    }
+}
@@ -0,0 +1,134 @@
+use sea_orm::{
+    sea_query::StringLen, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
+    ConnectionTrait, DbErr, DeleteResult, DeriveActiveEnum, DeriveEntityModel, DerivePrimaryKey,
+    DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(32))")]
+pub enum AuditEventKind {
+    #[sea_orm(string_value = "login_succeeded")]
+    LoginSucceeded,
+    #[sea_orm(string_value = "login_failed")]
+    LoginFailed,
+    #[sea_orm(string_value = "registered")]
+    Registered,
+    #[sea_orm(string_value = "verified")]
+    Verified,
+    #[sea_orm(string_value = "verification_failed")]
+    VerificationFailed,
+    #[sea_orm(string_value = "password_changed")]
+    PasswordChanged,
+    #[sea_orm(string_value = "provider_linked")]
+    ProviderLinked,
+    #[sea_orm(string_value = "provider_unlinked")]
+    ProviderUnlinked,
+    #[sea_orm(string_value = "locked_out")]
+    LockedOut,
+}
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_auth__audit_log")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id_number: i64,
+
+    pub kind: AuditEventKind,
+    pub auth_provider: String,
+
+    /// `None` when the event can't be tied to a user, e.g. a login to an unknown email.
+    #[sea_orm(index)]
+    pub user_id: Option<Uuid>,
+
+    /// Account identifier as presented by the client, such as the email address.
+    pub subject: Option<String>,
+
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub detail: Option<String>,
+
+    #[sea_orm(index)]
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type AuditLogData = Model;
+pub type AuditLogEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditLogBeforeInsert {
+    pub kind: AuditEventKind,
+    pub auth_provider: String,
+    pub user_id: Option<Uuid>,
+    pub subject: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub detail: Option<String>,
+}
+
+impl AuditLogData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: AuditLogBeforeInsert,
+    ) -> Result<AuditLogData, DbErr> {
+        ActiveModel {
+            kind: Set(data.kind),
+            auth_provider: Set(data.auth_provider),
+            user_id: Set(data.user_id),
+            subject: Set(data.subject),
+            ip: Set(data.ip),
+            user_agent: Set(data.user_agent),
+            detail: Set(data.detail),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Events of `user_id` in `[from, to)`, newest first.
+    pub async fn find_by_user_in_range(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        from: chrono::NaiveDateTime,
+        to: chrono::NaiveDateTime,
+    ) -> Result<Vec<AuditLogData>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::CreatedAt.gte(from))
+            .filter(Column::CreatedAt.lt(to))
+            .order_by_desc(Column::CreatedAt)
+            .all(db)
+            .await
+    }
+
+    /// Events of every user in `[from, to)`, newest first.
+    pub async fn find_in_range(
+        db: &impl ConnectionTrait,
+        from: chrono::NaiveDateTime,
+        to: chrono::NaiveDateTime,
+    ) -> Result<Vec<AuditLogData>, DbErr> {
+        Entity::find()
+            .filter(Column::CreatedAt.gte(from))
+            .filter(Column::CreatedAt.lt(to))
+            .order_by_desc(Column::CreatedAt)
+            .all(db)
+            .await
+    }
+
+    pub async fn delete_created_before(
+        db: &impl ConnectionTrait,
+        before: chrono::NaiveDateTime,
+    ) -> Result<DeleteResult, DbErr> {
+        Entity::delete_many()
+            .filter(Column::CreatedAt.lt(before))
+            .exec(db)
+            .await
+    }
+}
@@ -1,7 +1,7 @@
 use sea_orm::{
-    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
-    DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter,
-    PrimaryKeyTrait, QueryFilter,
+    prelude::Expr, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
+    ConnectionTrait, DbErr, DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait, QueryFilter,
 };
 use std::option::Option;
 use uuid::Uuid;
@@ -20,6 +20,10 @@ pub struct Model {
 
     pub verify_code: Option<String>,
     pub code_sent_at: Option<chrono::NaiveDateTime>,
+
+    /// Codes presented since `verify_code` was sent.
+    #[sea_orm(default_value = 0)]
+    pub verify_attempts: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -60,9 +64,52 @@ impl InnerEmailProviderData {
         let mut active: ActiveModel = before.clone().into();
         active.verify_code = Set(Some(verify_code));
         active.code_sent_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active.verify_attempts = Set(0);
         active.update(db).await
     }
 
+    /// Count a presented code, unless `max_attempts` codes were already presented since it
+    /// was sent. Returns `false` when the attempts are used up.
+    ///
+    /// Counted in a single statement, so parallel guesses can't exceed `max_attempts`.
+    pub async fn record_verify_attempt(
+        db: &impl ConnectionTrait,
+        before: &InnerEmailProviderData,
+        max_attempts: i32,
+    ) -> Result<bool, DbErr> {
+        let result = Entity::update_many()
+            .col_expr(
+                Column::VerifyAttempts,
+                Expr::col(Column::VerifyAttempts).add(1),
+            )
+            .filter(Column::Email.eq(before.email.clone()))
+            .filter(Column::VerifyAttempts.lt(max_attempts))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    /// Clear the code if it's still `verify_code`, returning whether it was, so concurrent
+    /// requests can't both use it.
+    pub async fn consume_verify_code(
+        db: &impl ConnectionTrait,
+        before: &InnerEmailProviderData,
+        verify_code: &str,
+    ) -> Result<bool, DbErr> {
+        let result = Entity::update_many()
+            .col_expr(Column::VerifyCode, Expr::value(Option::<String>::None))
+            .col_expr(
+                Column::CodeSentAt,
+                Expr::value(Option::<chrono::NaiveDateTime>::None),
+            )
+            .col_expr(Column::VerifyAttempts, Expr::value(0))
+            .filter(Column::Email.eq(before.email.clone()))
+            .filter(Column::VerifyCode.eq(verify_code))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+
     pub async fn find_by_auth_key(
         db: &impl ConnectionTrait,
         auth_key: Uuid,
@@ -0,0 +1,125 @@
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelBehavior, ActiveValue::Set, ColumnTrait, ConnectionTrait,
+    DbBackend, DbErr, DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait, QueryFilter, Statement,
+};
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_auth__login_attempt")]
+pub struct Model {
+    /// Scope and value of the throttled subject, e.g. `email:alice@example.com`.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub throttle_key: String,
+
+    pub failures: i32,
+    pub window_started_at: chrono::NaiveDateTime,
+
+    #[sea_orm(index)]
+    pub last_failure_at: chrono::NaiveDateTime,
+    pub locked_until: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type LoginAttemptData = Model;
+pub type LoginAttemptEntity = Entity;
+
+impl LoginAttemptData {
+    pub async fn find_by_key(
+        db: &impl ConnectionTrait,
+        throttle_key: &str,
+    ) -> Result<Option<LoginAttemptData>, DbErr> {
+        Entity::find_by_id(throttle_key.to_owned()).one(db).await
+    }
+
+    /// Insert the record, or overwrite the one with the same `throttle_key`.
+    pub async fn save(db: &impl ConnectionTrait, data: LoginAttemptData) -> Result<(), DbErr> {
+        Entity::insert(ActiveModel {
+            throttle_key: Set(data.throttle_key),
+            failures: Set(data.failures),
+            window_started_at: Set(data.window_started_at),
+            last_failure_at: Set(data.last_failure_at),
+            locked_until: Set(data.locked_until),
+        })
+        .on_conflict(
+            OnConflict::column(Column::ThrottleKey)
+                .update_columns([
+                    Column::Failures,
+                    Column::WindowStartedAt,
+                    Column::LastFailureAt,
+                    Column::LockedUntil,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Count a failure at `now` in a single statement, so concurrent failures are all
+    /// counted. Records whose window started before `window_started_after`, or whose
+    /// lockout ended, start over.
+    ///
+    /// The record is locked until `lock_until` when its failures reach `lockout_threshold`.
+    pub async fn increment(
+        db: &impl ConnectionTrait,
+        throttle_key: &str,
+        now: chrono::NaiveDateTime,
+        window_started_after: chrono::NaiveDateTime,
+        lockout_threshold: Option<i32>,
+        lock_until: chrono::NaiveDateTime,
+    ) -> Result<LoginAttemptData, DbErr> {
+        let maybe_record = Entity::find()
+            .from_raw_sql(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"
+                INSERT INTO ygg_auth__login_attempt AS attempt
+                    (throttle_key, failures, window_started_at, last_failure_at, locked_until)
+                VALUES ($1, 1, $2, $2, CASE WHEN 1 >= $4 THEN $5 END)
+                ON CONFLICT (throttle_key) DO UPDATE SET
+                    failures = CASE WHEN attempt.window_started_at < $3 OR attempt.locked_until <= $2
+                        THEN 1 ELSE attempt.failures + 1 END,
+                    window_started_at = CASE WHEN attempt.window_started_at < $3 OR attempt.locked_until <= $2
+                        THEN $2 ELSE attempt.window_started_at END,
+                    last_failure_at = $2,
+                    locked_until = CASE
+                        WHEN attempt.window_started_at < $3 OR attempt.locked_until <= $2
+                            THEN CASE WHEN 1 >= $4 THEN $5 END
+                        WHEN attempt.locked_until IS NULL AND attempt.failures + 1 >= $4 THEN $5
+                        ELSE attempt.locked_until END
+                RETURNING *
+                "#,
+                [
+                    throttle_key.into(),
+                    now.into(),
+                    window_started_after.into(),
+                    lockout_threshold.into(),
+                    lock_until.into(),
+                ],
+            ))
+            .one(db)
+            .await?;
+        maybe_record.ok_or_else(|| DbErr::RecordNotFound(throttle_key.to_owned()))
+    }
+
+    pub async fn delete_by_key(
+        db: &impl ConnectionTrait,
+        throttle_key: &str,
+    ) -> Result<DeleteResult, DbErr> {
+        Entity::delete_by_id(throttle_key.to_owned()).exec(db).await
+    }
+
+    /// Remove records whose last failure happened before `before`.
+    pub async fn delete_stale(
+        db: &impl ConnectionTrait,
+        before: chrono::NaiveDateTime,
+    ) -> Result<DeleteResult, DbErr> {
+        Entity::delete_many()
+            .filter(Column::LastFailureAt.lt(before))
+            .exec(db)
+            .await
+    }
+}
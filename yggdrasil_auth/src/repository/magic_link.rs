@@ -0,0 +1,75 @@
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter,
+    PrimaryKeyTrait, QueryFilter,
+};
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_auth__magic_link")]
+pub struct Model {
+    /// Hex encoded SHA-256 of the token sent in the link.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub token_hash: String,
+
+    #[sea_orm(index)]
+    pub email: String,
+
+    pub created_at: chrono::NaiveDateTime,
+
+    #[sea_orm(index)]
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type MagicLinkData = Model;
+pub type MagicLinkEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MagicLinkBeforeInsert {
+    pub token_hash: String,
+    pub email: String,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+impl MagicLinkData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: MagicLinkBeforeInsert,
+    ) -> Result<MagicLinkData, DbErr> {
+        ActiveModel {
+            token_hash: Set(data.token_hash),
+            email: Set(data.email),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            expires_at: Set(data.expires_at),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Find and delete the link, so it can be used only once even by concurrent requests.
+    pub async fn take(
+        db: &impl ConnectionTrait,
+        token_hash: &str,
+    ) -> Result<Option<MagicLinkData>, DbErr> {
+        let maybe_link = Entity::find_by_id(token_hash.to_owned()).one(db).await?;
+        if maybe_link.is_none() {
+            return Ok(None);
+        }
+        let deleted = Entity::delete_by_id(token_hash.to_owned()).exec(db).await?;
+        Ok(maybe_link.filter(|_| deleted.rows_affected == 1))
+    }
+
+    pub async fn delete_expired(
+        db: &impl ConnectionTrait,
+        now: chrono::NaiveDateTime,
+    ) -> Result<DeleteResult, DbErr> {
+        Entity::delete_many()
+            .filter(Column::ExpiresAt.lt(now))
+            .exec(db)
+            .await
+    }
+}
@@ -1,7 +1,31 @@
+mod api_key;
+mod audit_log;
 mod inner_email_provider;
+mod login_attempt;
+mod magic_link;
+mod passkey_challenge;
+mod passkey_credential;
+mod phone_provider;
+mod role;
+mod role_permission;
 mod user_auth_pair;
+mod user_role;
 
+pub use api_key::{ApiKeyBeforeInsert, ApiKeyData, ApiKeyEntity};
+pub use audit_log::{AuditEventKind, AuditLogBeforeInsert, AuditLogData, AuditLogEntity};
 pub use inner_email_provider::{
     InnerEmailProviderBeforeInsert, InnerEmailProviderData, InnerEmailProviderEntity,
 };
+pub use login_attempt::{LoginAttemptData, LoginAttemptEntity};
+pub use magic_link::{MagicLinkBeforeInsert, MagicLinkData, MagicLinkEntity};
+pub use passkey_challenge::{
+    PasskeyChallengeBeforeInsert, PasskeyChallengeData, PasskeyChallengeEntity,
+};
+pub use passkey_credential::{
+    PasskeyCredentialBeforeInsert, PasskeyCredentialData, PasskeyCredentialEntity,
+};
+pub use phone_provider::{PhoneProviderBeforeInsert, PhoneProviderData, PhoneProviderEntity};
+pub use role::{RoleBeforeInsert, RoleData, RoleEntity};
+pub use role_permission::{RolePermissionData, RolePermissionEntity};
 pub use user_auth_pair::{UserAuthPairBeforeInsert, UserAuthPairData, UserAuthPairEntity};
+pub use user_role::{UserRoleBeforeInsert, UserRoleData, UserRoleEntity};
@@ -0,0 +1,78 @@
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter,
+    PrimaryKeyTrait, QueryFilter,
+};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_auth__passkey_challenge")]
+pub struct Model {
+    /// Base64url (no padding) encoded challenge, as echoed back in `clientDataJSON`.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub challenge: String,
+
+    /// `registration` or `authentication`.
+    pub ceremony: String,
+
+    /// The user a registration challenge was issued for.
+    pub user_id: Option<Uuid>,
+
+    #[sea_orm(index)]
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type PasskeyChallengeData = Model;
+pub type PasskeyChallengeEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasskeyChallengeBeforeInsert {
+    pub challenge: String,
+    pub ceremony: String,
+    pub user_id: Option<Uuid>,
+}
+
+impl PasskeyChallengeData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: PasskeyChallengeBeforeInsert,
+    ) -> Result<PasskeyChallengeData, DbErr> {
+        ActiveModel {
+            challenge: Set(data.challenge),
+            ceremony: Set(data.ceremony),
+            user_id: Set(data.user_id),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Find and delete a challenge, so that every challenge can only be answered once, even
+    /// by concurrent requests.
+    pub async fn take(
+        db: &impl ConnectionTrait,
+        challenge: &str,
+    ) -> Result<Option<PasskeyChallengeData>, DbErr> {
+        let maybe_challenge = Entity::find_by_id(challenge.to_owned()).one(db).await?;
+        if maybe_challenge.is_none() {
+            return Ok(None);
+        }
+        let deleted = Entity::delete_by_id(challenge.to_owned()).exec(db).await?;
+        Ok(maybe_challenge.filter(|_| deleted.rows_affected == 1))
+    }
+
+    pub async fn delete_created_before(
+        db: &impl ConnectionTrait,
+        before: chrono::NaiveDateTime,
+    ) -> Result<DeleteResult, DbErr> {
+        Entity::delete_many()
+            .filter(Column::CreatedAt.lt(before))
+            .exec(db)
+            .await
+    }
+}
@@ -0,0 +1,111 @@
+use sea_orm::{
+    prelude::Expr, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition,
+    ConnectionTrait, DbErr, DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait, QueryFilter,
+};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_auth__passkey_credential")]
+pub struct Model {
+    /// Base64url (no padding) encoded credential id chosen by the authenticator.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub credential_id: String,
+
+    /// SEC1 encoded P-256 public key extracted from the attestation.
+    pub public_key: Vec<u8>,
+
+    #[sea_orm(index, unique)]
+    pub auth_key: Uuid,
+
+    #[sea_orm(default_value = 0)]
+    pub sign_count: i64,
+
+    pub created_at: chrono::NaiveDateTime,
+    pub last_used_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type PasskeyCredentialData = Model;
+pub type PasskeyCredentialEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasskeyCredentialBeforeInsert {
+    pub credential_id: String,
+    pub public_key: Vec<u8>,
+    pub auth_key: Uuid,
+    pub sign_count: i64,
+}
+
+impl PasskeyCredentialData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: PasskeyCredentialBeforeInsert,
+    ) -> Result<PasskeyCredentialData, DbErr> {
+        ActiveModel {
+            credential_id: Set(data.credential_id),
+            public_key: Set(data.public_key),
+            auth_key: Set(data.auth_key),
+            sign_count: Set(data.sign_count),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    pub async fn find_by_credential_id(
+        db: &impl ConnectionTrait,
+        credential_id: &str,
+    ) -> Result<Option<PasskeyCredentialData>, DbErr> {
+        Entity::find_by_id(credential_id.to_owned()).one(db).await
+    }
+
+    pub async fn find_by_auth_key(
+        db: &impl ConnectionTrait,
+        auth_key: Uuid,
+    ) -> Result<Option<PasskeyCredentialData>, DbErr> {
+        Entity::find()
+            .filter(Column::AuthKey.eq(auth_key))
+            .one(db)
+            .await
+    }
+
+    /// Store `sign_count` if it's above the stored counter, or both are zero for
+    /// authenticators without a counter. Returns `None` when it isn't, e.g. because a
+    /// concurrent assertion stored the same or a higher count first.
+    pub async fn update_sign_count(
+        db: &impl ConnectionTrait,
+        before: &PasskeyCredentialData,
+        sign_count: i64,
+    ) -> Result<Option<PasskeyCredentialData>, DbErr> {
+        let counter_increased = Condition::any().add(Column::SignCount.lt(sign_count)).add(
+            Condition::all()
+                .add(Column::SignCount.eq(0))
+                .add(Expr::value(sign_count).eq(0)),
+        );
+        let updated = Entity::update_many()
+            .col_expr(Column::SignCount, Expr::value(sign_count))
+            .col_expr(
+                Column::LastUsedAt,
+                Expr::value(Some(chrono::Utc::now().naive_utc())),
+            )
+            .filter(Column::CredentialId.eq(before.credential_id.clone()))
+            .filter(counter_increased)
+            .exec_with_returning(db)
+            .await?;
+        Ok(updated.into_iter().next())
+    }
+
+    pub async fn delete(
+        db: &impl ConnectionTrait,
+        before: PasskeyCredentialData,
+    ) -> Result<DeleteResult, DbErr> {
+        let active: ActiveModel = before.into();
+        Entity::delete(active).exec(db).await
+    }
+}
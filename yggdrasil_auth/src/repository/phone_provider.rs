@@ -0,0 +1,137 @@
+use sea_orm::{
+    prelude::Expr, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
+    ConnectionTrait, DbErr, DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait, QueryFilter,
+};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_auth__phone_provider")]
+pub struct Model {
+    /// E.164 formatted, e.g. `+14155550123`.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub phone_number: String,
+
+    #[sea_orm(index, unique)]
+    pub auth_key: Uuid,
+
+    pub verify_code: Option<String>,
+    pub code_sent_at: Option<chrono::NaiveDateTime>,
+
+    /// Codes presented since `verify_code` was sent.
+    #[sea_orm(default_value = 0)]
+    pub verify_attempts: i32,
+
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type PhoneProviderData = Model;
+pub type PhoneProviderEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhoneProviderBeforeInsert {
+    pub phone_number: String,
+    pub auth_key: Uuid,
+}
+
+impl PhoneProviderData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: PhoneProviderBeforeInsert,
+    ) -> Result<PhoneProviderData, DbErr> {
+        ActiveModel {
+            phone_number: Set(data.phone_number),
+            auth_key: Set(data.auth_key),
+            verify_code: Set(None),
+            code_sent_at: Set(None),
+            verify_attempts: Set(0),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+        }
+        .insert(db)
+        .await
+    }
+
+    pub async fn find_by_phone_number(
+        db: &impl ConnectionTrait,
+        phone_number: &str,
+    ) -> Result<Option<PhoneProviderData>, DbErr> {
+        Entity::find_by_id(phone_number.to_owned()).one(db).await
+    }
+
+    pub async fn find_by_auth_key(
+        db: &impl ConnectionTrait,
+        auth_key: Uuid,
+    ) -> Result<Option<PhoneProviderData>, DbErr> {
+        Entity::find()
+            .filter(Column::AuthKey.eq(auth_key))
+            .one(db)
+            .await
+    }
+
+    pub async fn set_verify_code(
+        db: &impl ConnectionTrait,
+        before: &PhoneProviderData,
+        verify_code: String,
+    ) -> Result<PhoneProviderData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.verify_code = Set(Some(verify_code));
+        active.code_sent_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active.verify_attempts = Set(0);
+        active.update(db).await
+    }
+
+    /// Count a presented code, unless `max_attempts` codes were already presented since it
+    /// was sent. Returns `false` when the attempts are used up.
+    ///
+    /// Counted in a single statement, so parallel guesses can't exceed `max_attempts`.
+    pub async fn record_verify_attempt(
+        db: &impl ConnectionTrait,
+        before: &PhoneProviderData,
+        max_attempts: i32,
+    ) -> Result<bool, DbErr> {
+        let result = Entity::update_many()
+            .col_expr(
+                Column::VerifyAttempts,
+                Expr::col(Column::VerifyAttempts).add(1),
+            )
+            .filter(Column::PhoneNumber.eq(before.phone_number.clone()))
+            .filter(Column::VerifyAttempts.lt(max_attempts))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    /// Clear the code if it's still `verify_code`, returning whether it was, so concurrent
+    /// requests can't both use it.
+    pub async fn consume_verify_code(
+        db: &impl ConnectionTrait,
+        before: &PhoneProviderData,
+        verify_code: &str,
+    ) -> Result<bool, DbErr> {
+        let result = Entity::update_many()
+            .col_expr(Column::VerifyCode, Expr::value(Option::<String>::None))
+            .col_expr(
+                Column::CodeSentAt,
+                Expr::value(Option::<chrono::NaiveDateTime>::None),
+            )
+            .col_expr(Column::VerifyAttempts, Expr::value(0))
+            .filter(Column::PhoneNumber.eq(before.phone_number.clone()))
+            .filter(Column::VerifyCode.eq(verify_code))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    pub async fn delete(
+        db: &impl ConnectionTrait,
+        before: PhoneProviderData,
+    ) -> Result<DeleteResult, DbErr> {
+        let active: ActiveModel = before.into();
+        Entity::delete(active).exec(db).await
+    }
+}
@@ -0,0 +1,81 @@
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter,
+    PrimaryKeyTrait, QueryFilter, QueryOrder,
+};
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_auth__role")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id_number: i32,
+
+    #[sea_orm(unique)]
+    pub name: String,
+
+    pub description: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type RoleData = Model;
+pub type RoleEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleBeforeInsert {
+    pub name: String,
+    pub description: String,
+}
+
+impl RoleData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: RoleBeforeInsert,
+    ) -> Result<RoleData, DbErr> {
+        ActiveModel {
+            name: Set(data.name),
+            description: Set(data.description),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    pub async fn find_by_id(db: &impl ConnectionTrait, id: i32) -> Result<Option<RoleData>, DbErr> {
+        Entity::find_by_id(id).one(db).await
+    }
+
+    pub async fn find_by_name(
+        db: &impl ConnectionTrait,
+        name: &str,
+    ) -> Result<Option<RoleData>, DbErr> {
+        Entity::find().filter(Column::Name.eq(name)).one(db).await
+    }
+
+    pub async fn list(db: &impl ConnectionTrait) -> Result<Vec<RoleData>, DbErr> {
+        Entity::find().order_by_asc(Column::Name).all(db).await
+    }
+
+    pub async fn update_description(
+        db: &impl ConnectionTrait,
+        before: &RoleData,
+        new_description: &str,
+    ) -> Result<RoleData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.description = Set(new_description.to_owned());
+        active.update(db).await
+    }
+
+    pub async fn delete(
+        db: &impl ConnectionTrait,
+        before: RoleData,
+    ) -> Result<DeleteResult, DbErr> {
+        let active: ActiveModel = before.into();
+        Entity::delete(active).exec(db).await
+    }
+}
@@ -0,0 +1,85 @@
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter,
+    PrimaryKeyTrait, QueryFilter,
+};
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_auth__role_permission")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id_number: i32,
+
+    /// (`role_id`, `permission`) is unique.
+    #[sea_orm(index)]
+    pub role_id: i32,
+
+    /// Dot separated permission such as `shop.production.write`, `*` matches any segments.
+    pub permission: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type RolePermissionData = Model;
+pub type RolePermissionEntity = Entity;
+
+impl RolePermissionData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        role_id: i32,
+        permission: &str,
+    ) -> Result<RolePermissionData, DbErr> {
+        ActiveModel {
+            role_id: Set(role_id),
+            permission: Set(permission.to_owned()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    pub async fn find_by_role_id(
+        db: &impl ConnectionTrait,
+        role_id: i32,
+    ) -> Result<Vec<RolePermissionData>, DbErr> {
+        Entity::find()
+            .filter(Column::RoleId.eq(role_id))
+            .all(db)
+            .await
+    }
+
+    pub async fn find_by_role_ids(
+        db: &impl ConnectionTrait,
+        role_ids: Vec<i32>,
+    ) -> Result<Vec<RolePermissionData>, DbErr> {
+        Entity::find()
+            .filter(Column::RoleId.is_in(role_ids))
+            .all(db)
+            .await
+    }
+
+    pub async fn delete_by_role_and_permission(
+        db: &impl ConnectionTrait,
+        role_id: i32,
+        permission: &str,
+    ) -> Result<DeleteResult, DbErr> {
+        Entity::delete_many()
+            .filter(Column::RoleId.eq(role_id))
+            .filter(Column::Permission.eq(permission))
+            .exec(db)
+            .await
+    }
+
+    pub async fn delete_by_role_id(
+        db: &impl ConnectionTrait,
+        role_id: i32,
+    ) -> Result<DeleteResult, DbErr> {
+        Entity::delete_many()
+            .filter(Column::RoleId.eq(role_id))
+            .exec(db)
+            .await
+    }
+}
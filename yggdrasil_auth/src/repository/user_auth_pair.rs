@@ -1,7 +1,7 @@
 use sea_orm::{
     ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
     DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter,
-    PrimaryKeyTrait, QueryFilter,
+    PrimaryKeyTrait, QueryFilter, QuerySelect,
 };
 use uuid::Uuid;
 
@@ -11,6 +11,7 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id_number: i32,
 
+    /// (`auth_provider`, `auth_key`) is unique.
     #[sea_orm(index)]
     pub auth_provider: String,
 
@@ -80,6 +81,19 @@ impl UserAuthPairData {
             .await
     }
 
+    /// Like [UserAuthPairData::find_by_user_id], locking the pairs until the end of the
+    /// transaction so concurrent changes to the user's login methods wait for each other.
+    pub async fn find_by_user_id_for_update(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+    ) -> Result<Vec<UserAuthPairData>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .lock_exclusive()
+            .all(db)
+            .await
+    }
+
     pub async fn find_by_key(
         db: &impl ConnectionTrait,
         auth_provider: &str,
@@ -0,0 +1,112 @@
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter,
+    PrimaryKeyTrait, QueryFilter,
+};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_auth__user_role")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id_number: i32,
+
+    #[sea_orm(index)]
+    pub user_id: Uuid,
+
+    #[sea_orm(index)]
+    pub role_id: i32,
+
+    /// The role only applies to this resource, e.g. `shop.production:42`, when set.
+    pub resource: Option<String>,
+
+    pub granted_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type UserRoleData = Model;
+pub type UserRoleEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserRoleBeforeInsert {
+    pub user_id: Uuid,
+    pub role_id: i32,
+    pub resource: Option<String>,
+}
+
+impl UserRoleData {
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: UserRoleBeforeInsert,
+    ) -> Result<UserRoleData, DbErr> {
+        ActiveModel {
+            user_id: Set(data.user_id),
+            role_id: Set(data.role_id),
+            resource: Set(data.resource),
+            granted_at: Set(chrono::Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Find the assignment of `role_id` to `user_id` on exactly `resource`.
+    pub async fn find_assignment(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+        role_id: i32,
+        resource: Option<&str>,
+    ) -> Result<Option<UserRoleData>, DbErr> {
+        let query = Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::RoleId.eq(role_id));
+        match resource {
+            Some(resource) => query.filter(Column::Resource.eq(resource)),
+            None => query.filter(Column::Resource.is_null()),
+        }
+        .one(db)
+        .await
+    }
+
+    pub async fn find_by_user_id(
+        db: &impl ConnectionTrait,
+        user_id: Uuid,
+    ) -> Result<Vec<UserRoleData>, DbErr> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .all(db)
+            .await
+    }
+
+    pub async fn find_by_role_id(
+        db: &impl ConnectionTrait,
+        role_id: i32,
+    ) -> Result<Vec<UserRoleData>, DbErr> {
+        Entity::find()
+            .filter(Column::RoleId.eq(role_id))
+            .all(db)
+            .await
+    }
+
+    pub async fn delete(
+        db: &impl ConnectionTrait,
+        before: UserRoleData,
+    ) -> Result<DeleteResult, DbErr> {
+        let active: ActiveModel = before.into();
+        Entity::delete(active).exec(db).await
+    }
+
+    pub async fn delete_by_role_id(
+        db: &impl ConnectionTrait,
+        role_id: i32,
+    ) -> Result<DeleteResult, DbErr> {
+        Entity::delete_many()
+            .filter(Column::RoleId.eq(role_id))
+            .exec(db)
+            .await
+    }
+}
@@ -0,0 +1,55 @@
+use crate::auth_provider::AuthError;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmsMessage {
+    /// E.164 formatted recipient, e.g. `+14155550123`.
+    pub to: String,
+    pub body: String,
+}
+
+/// Delivers text messages, implement it for the SMS vendor in use.
+#[async_trait::async_trait]
+pub trait SmsGateway: Send + Sync {
+    async fn send(&self, message: SmsMessage) -> Result<(), AuthError>;
+}
+
+/// Keeps sent messages in memory so tests can assert on them.
+#[derive(Default)]
+pub struct MemorySmsGateway {
+    sent: Mutex<Vec<SmsMessage>>,
+}
+
+impl MemorySmsGateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every message sent so far, oldest first.
+    pub fn sent(&self) -> Vec<SmsMessage> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    /// The most recent message sent to `to`.
+    pub fn last_sent_to(&self, to: &str) -> Option<SmsMessage> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|message| message.to == to)
+            .cloned()
+    }
+
+    pub fn clear(&self) {
+        self.sent.lock().unwrap().clear();
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsGateway for MemorySmsGateway {
+    async fn send(&self, message: SmsMessage) -> Result<(), AuthError> {
+        self.sent.lock().unwrap().push(message);
+        Ok(())
+    }
+}
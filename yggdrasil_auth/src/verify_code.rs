@@ -0,0 +1,84 @@
+use crate::auth_provider::AuthError;
+use chrono::{Duration, NaiveDateTime};
+use rand::rngs::OsRng;
+use rand::Rng;
+
+/// How long verification codes stay valid and how many wrong guesses they tolerate.
+///
+/// Shared by every provider sending codes out of band, such as email and SMS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyCodePolicy {
+    pub ttl: Duration,
+    /// Wrong codes accepted before the code is burned and a new one must be sent.
+    pub max_attempts: i32,
+    /// Digits in codes made by [VerifyCodePolicy::generate].
+    pub code_length: usize,
+}
+
+impl Default for VerifyCodePolicy {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::minutes(10),
+            max_attempts: 5,
+            code_length: 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyCodeStatus {
+    Accepted,
+    Rejected,
+    /// No code has been sent, or it was already used.
+    Missing,
+    Expired,
+    AttemptsExhausted,
+}
+
+impl VerifyCodeStatus {
+    /// Whether the code was accepted, failing when the code can't be accepted anymore so
+    /// the client knows to request a new one.
+    pub fn into_result(self) -> Result<bool, AuthError> {
+        match self {
+            VerifyCodeStatus::Accepted => Ok(true),
+            VerifyCodeStatus::Rejected | VerifyCodeStatus::Missing => Ok(false),
+            VerifyCodeStatus::Expired => Err(AuthError::CodeExpired),
+            VerifyCodeStatus::AttemptsExhausted => Err(AuthError::CodeAttemptsExhausted),
+        }
+    }
+}
+
+impl VerifyCodePolicy {
+    /// A random numeric code.
+    pub fn generate(&self) -> String {
+        let mut rng = OsRng;
+        (0..self.code_length)
+            .map(|_| char::from(b'0' + rng.gen_range(0..10)))
+            .collect()
+    }
+
+    /// Check `presented` against the stored code, sent at `sent_at` and already guessed
+    /// wrong `attempts` times.
+    pub fn check(
+        &self,
+        stored: Option<&str>,
+        sent_at: Option<NaiveDateTime>,
+        attempts: i32,
+        presented: &str,
+        now: NaiveDateTime,
+    ) -> VerifyCodeStatus {
+        let Some(stored) = stored else {
+            return VerifyCodeStatus::Missing;
+        };
+        if sent_at.is_none_or(|sent_at| sent_at + self.ttl < now) {
+            return VerifyCodeStatus::Expired;
+        }
+        if attempts >= self.max_attempts {
+            return VerifyCodeStatus::AttemptsExhausted;
+        }
+        match stored == presented {
+            true => VerifyCodeStatus::Accepted,
+            false => VerifyCodeStatus::Rejected,
+        }
+    }
+}
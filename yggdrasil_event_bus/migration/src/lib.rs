@@ -0,0 +1,18 @@
+pub use sea_orm_migration::prelude::*;
+
+mod m20261019_000001_create_outbox_tables;
+
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl MigratorTrait for Migrator {
+    fn migration_table_name() -> DynIden {
+        Alias::new("ygg_event_bus__seaql_migrations").into_iden()
+    }
+
+    fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+        vec![
+            Box::new(m20261019_000001_create_outbox_tables::Migration),
+        ]
+    }
+}
@@ -0,0 +1,86 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum BusOutbox {
+    #[sea_orm(iden = "ygg_event_bus__outbox")]
+    Table,
+    Id,
+    EventId,
+    EventName,
+    Payload,
+    Attempts,
+    NextAttemptAt,
+    LastError,
+    CreatedAt,
+    DeliveredAt,
+    FailedAt,
+}
+
+#[derive(DeriveIden)]
+enum BusInbox {
+    #[sea_orm(iden = "ygg_event_bus__inbox")]
+    Table,
+    Consumer,
+    EventName,
+    EventId,
+    ProcessedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(BusOutbox::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(BusOutbox::Id).integer().not_null().auto_increment().primary_key())
+                .col(ColumnDef::new(BusOutbox::EventId).uuid().not_null())
+                .col(ColumnDef::new(BusOutbox::EventName).string().not_null())
+                .col(ColumnDef::new(BusOutbox::Payload).text().not_null())
+                .col(ColumnDef::new(BusOutbox::Attempts).integer().not_null().default(0))
+                .col(ColumnDef::new(BusOutbox::NextAttemptAt).timestamp().not_null())
+                .col(ColumnDef::new(BusOutbox::LastError).text().null())
+                .col(ColumnDef::new(BusOutbox::CreatedAt).timestamp().not_null())
+                .col(ColumnDef::new(BusOutbox::DeliveredAt).timestamp().null())
+                .col(ColumnDef::new(BusOutbox::FailedAt).timestamp().null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(BusOutbox::Table)
+                .name("ygg_event_bus__outbox_next_attempt_at_index")
+                .col(BusOutbox::NextAttemptAt)
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(BusOutbox::Table)
+                .name("ygg_event_bus__outbox_event_name_event_id_index")
+                .col(BusOutbox::EventName)
+                .col(BusOutbox::EventId)
+                .unique()
+                .to_owned()
+        ).await?;
+        manager.create_table(
+            Table::create()
+                .table(BusInbox::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(BusInbox::Consumer).string().not_null())
+                .col(ColumnDef::new(BusInbox::EventName).string().not_null())
+                .col(ColumnDef::new(BusInbox::EventId).uuid().not_null())
+                .col(ColumnDef::new(BusInbox::ProcessedAt).timestamp().not_null())
+                .primary_key(Index::create().col(BusInbox::Consumer).col(BusInbox::EventName).col(BusInbox::EventId))
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(BusInbox::Table).to_owned()).await?;
+        manager.drop_table(Table::drop().table(BusOutbox::Table).to_owned()).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use crate::bus::EventHandler;
+use crate::error::BoxError;
+use crate::events::{AffiliateRewardRecorded, OrderCanceled, OrderFulfilled, UserRegistered};
+use crate::outbox::enqueue;
+use async_trait::async_trait;
+use sea_orm::{DatabaseConnection, TransactionTrait};
+use std::sync::Arc;
+use tracing::warn;
+use yggdrasil_affaliate::error::AffiliateError;
+use yggdrasil_affaliate::event_handler::{write_event_in_transaction, AffiliateEvent, AffiliateSettings};
+use yggdrasil_affaliate::referral::resolve_inviter;
+use yggdrasil_affaliate::repository::AffiliateAttributionData;
+use yggdrasil_affaliate::reward::reverse_rewards_for_order;
+
+/// Rewards the inviter of the buyer for fulfilled orders, and reverses the rewards of
+/// canceled ones.
+///
+/// Recorded rewards are announced with [AffiliateRewardRecorded] through the outbox.
+pub struct AffiliateOrderHandler {
+    database_connection: Arc<DatabaseConnection>,
+    settings: AffiliateSettings,
+}
+
+impl AffiliateOrderHandler {
+    pub fn new(database_connection: Arc<DatabaseConnection>, settings: AffiliateSettings) -> Self {
+        Self {
+            database_connection,
+            settings,
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler<OrderFulfilled> for AffiliateOrderHandler {
+    async fn handle(&self, event: &OrderFulfilled) -> Result<(), BoxError> {
+        let db = self.database_connection.as_ref();
+        let Some(attribution) = AffiliateAttributionData::find_by_user_id(db, event.buyer_id).await? else {
+            return Ok(());
+        };
+        let affiliate_event = AffiliateEvent {
+            event_id: event.event_id,
+            from: attribution.inviter_id,
+            to: event.buyer_id,
+            raw_value: event.amount,
+            order_reference: Some(event.order_reference.clone()),
+            product_type: event.product_type.clone(),
+        };
+        let tx = db.begin().await?;
+        let is_new = match write_event_in_transaction(&tx, &affiliate_event, &self.settings).await {
+            Ok(is_new) => is_new,
+            // Rejected orders would be rejected again, retrying them only clogs the bus.
+            Err(err @ AffiliateError::FraudRejected(_)) => {
+                warn!("Yggdrasil Event Bus: Didn't reward order ({}): {}", event.order_reference, err);
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if is_new {
+            enqueue(&tx, &AffiliateRewardRecorded {
+                event_id: event.event_id,
+                inviter_id: attribution.inviter_id,
+                invited_id: event.buyer_id,
+                order_reference: Some(event.order_reference.clone()),
+            }).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventHandler<OrderCanceled> for AffiliateOrderHandler {
+    async fn handle(&self, event: &OrderCanceled) -> Result<(), BoxError> {
+        let db = self.database_connection.as_ref();
+        match reverse_rewards_for_order(db, &event.order_reference).await {
+            Ok(_) | Err(AffiliateError::OrderNotFound(_)) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Attributes new users to the affiliate who referred them.
+pub struct AffiliateAttributionHandler {
+    database_connection: Arc<DatabaseConnection>,
+    settings: AffiliateSettings,
+}
+
+impl AffiliateAttributionHandler {
+    pub fn new(database_connection: Arc<DatabaseConnection>, settings: AffiliateSettings) -> Self {
+        Self {
+            database_connection,
+            settings,
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler<UserRegistered> for AffiliateAttributionHandler {
+    async fn handle(&self, event: &UserRegistered) -> Result<(), BoxError> {
+        if event.visitor_key.is_none() && event.referral_code.is_none() {
+            return Ok(());
+        }
+        let visitor_key = event.visitor_key.clone().unwrap_or_else(|| event.user_id.to_string());
+        let db = self.database_connection.as_ref();
+        let result = resolve_inviter(db, &visitor_key, event.referral_code.as_deref(), event.user_id, &self.settings).await;
+        match result {
+            Ok(_) => Ok(()),
+            // A mistyped code shouldn't be retried forever, the registration itself succeeded.
+            Err(err @ (AffiliateError::InvalidReferralCode(_) | AffiliateError::ReferralCodeNotFound(_))) => {
+                warn!("Yggdrasil Event Bus: Couldn't attribute user ({}): {}", event.user_id, err);
+                Ok(())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::bus::EventBus;
+use crate::error::EventBusError;
+use crate::events::UserRegistered;
+use crate::outbox::enqueue;
+use sea_orm::{DatabaseConnection, DbErr, TransactionTrait};
+use tracing::warn;
+use yggdrasil_auth::auth_provider::{AuthContext, AuthError, AuthProvider};
+use yggdrasil_auth::registration::{register_user, register_user_in};
+use yggdrasil_auth::repository::UserAuthPairData;
+use yggdrasil_user::repository::{UserBeforeInsert, UserData};
+
+/// How a visitor reached the registration, for affiliate attribution.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReferralInfo {
+    pub visitor_key: Option<String>,
+    pub referral_code: Option<String>,
+}
+
+/// [register_user], then publish [UserRegistered].
+///
+/// The user is registered even if a handler fails, failures are only logged.
+pub async fn register_user_and_publish<Account, Provider>(
+    db: &DatabaseConnection,
+    bus: &EventBus,
+    provider: &Provider,
+    account: &Account,
+    user: UserBeforeInsert,
+    referral: ReferralInfo,
+    context: &AuthContext,
+) -> Result<(UserData, UserAuthPairData), AuthError>
+where
+    Account: Send + Sync + Sized + Clone,
+    Provider: AuthProvider<Account>,
+{
+    let (user, pair) = register_user(db, provider, account, user, context).await?;
+    let event = UserRegistered {
+        user_id: user.id,
+        visitor_key: referral.visitor_key,
+        referral_code: referral.referral_code,
+    };
+    if let Err(err) = bus.publish(&event).await {
+        warn!("Yggdrasil Event Bus: Handling the registration of user ({}) failed: {}", user.id, err);
+    }
+    Ok((user, pair))
+}
+
+/// Like [register_user_and_publish], but [UserRegistered] is queued in the outbox within the
+/// transaction of [register_user_in], so it's delivered by the
+/// [OutboxRelay](crate::outbox::OutboxRelay) even if the process stops right after.
+pub async fn register_user_and_enqueue<Account, Provider>(
+    db: &DatabaseConnection,
+    provider: &Provider,
+    account: &Account,
+    user: UserBeforeInsert,
+    referral: ReferralInfo,
+    context: &AuthContext,
+) -> Result<(UserData, UserAuthPairData), AuthError>
+where
+    Account: Send + Sync + Sized + Clone,
+    Provider: AuthProvider<Account>,
+{
+    let tx = db.begin().await.map_err(AuthError::DatabaseError)?;
+    let (user, pair) = register_user_in(&tx, provider, account, user, context).await?;
+    let event = UserRegistered {
+        user_id: user.id,
+        visitor_key: referral.visitor_key,
+        referral_code: referral.referral_code,
+    };
+    enqueue(&tx, &event).await.map_err(into_auth_error)?;
+    tx.commit().await.map_err(AuthError::DatabaseError)?;
+    Ok((user, pair))
+}
+
+fn into_auth_error(err: EventBusError) -> AuthError {
+    match err {
+        EventBusError::Database(err) => AuthError::DatabaseError(err),
+        err => AuthError::DatabaseError(DbErr::Custom(err.to_string())),
+    }
+}
@@ -0,0 +1,3 @@
+pub mod affiliate;
+pub mod auth;
+pub mod shop;
@@ -0,0 +1,105 @@
+use crate::bus::EventBus;
+use crate::error::EventBusError;
+use crate::events::{OrderCanceled, OrderFulfilled};
+use crate::outbox::enqueue;
+use async_trait::async_trait;
+use sea_orm::ConnectionTrait;
+use std::sync::Arc;
+use uuid::Uuid;
+use yggdrasil_tiny_shop::error::ShopError;
+use yggdrasil_tiny_shop::event_handler::{OrderContext, ShopModuleEventHandler};
+
+/// [OrderContext::metadata] key of the product type published with [OrderFulfilled].
+pub const PRODUCT_TYPE_METADATA: &str = "product_type";
+
+/// Publishes the shop's order events on the bus. Register it after the handlers which
+/// may still adjust the order, or call it from the order code once the order's status
+/// change is committed.
+///
+/// Events published this way are lost if the process stops before they're handled, use
+/// [enqueue_order_fulfilled] and [enqueue_order_canceled] to deliver them through the outbox.
+pub struct ShopEventPublisher {
+    bus: Arc<EventBus>,
+}
+
+impl ShopEventPublisher {
+    pub fn new(bus: Arc<EventBus>) -> Self {
+        Self { bus }
+    }
+
+    /// Publish [OrderFulfilled], identified by the order so a retried fulfillment is
+    /// recognized as the same event.
+    pub async fn order_fulfilled(
+        &self,
+        order_id: Uuid,
+        buyer_id: Uuid,
+        amount: f32,
+        product_type: Option<String>,
+    ) -> Result<(), EventBusError> {
+        self.bus.publish(&order_fulfilled_event(order_id, buyer_id, amount, product_type)).await
+    }
+
+    /// Publish [OrderCanceled], for canceled and refunded orders alike.
+    pub async fn order_canceled(&self, order_id: Uuid) -> Result<(), EventBusError> {
+        self.bus.publish(&order_canceled_event(order_id)).await
+    }
+}
+
+/// Queue [OrderFulfilled] for `order` in the outbox. Call it in the transaction marking the
+/// order fulfilled, the [OutboxRelay](crate::outbox::OutboxRelay) delivers it once committed.
+pub async fn enqueue_order_fulfilled(
+    tx: &impl ConnectionTrait,
+    order: &OrderContext,
+) -> Result<bool, EventBusError> {
+    let product_type = order.metadata.get(PRODUCT_TYPE_METADATA).cloned();
+    enqueue(tx, &order_fulfilled_event(order.order_id, order.user_id, order.total, product_type)).await
+}
+
+/// Queue [OrderCanceled] for `order` in the outbox, in the transaction canceling or
+/// refunding the order.
+pub async fn enqueue_order_canceled(
+    tx: &impl ConnectionTrait,
+    order: &OrderContext,
+) -> Result<bool, EventBusError> {
+    enqueue(tx, &order_canceled_event(order.order_id)).await
+}
+
+/// Identified by the order, so a retried fulfillment is recognized as the same event.
+fn order_fulfilled_event(
+    order_id: Uuid,
+    buyer_id: Uuid,
+    amount: f32,
+    product_type: Option<String>,
+) -> OrderFulfilled {
+    OrderFulfilled {
+        event_id: order_id,
+        order_reference: order_id.to_string(),
+        buyer_id,
+        amount,
+        product_type,
+    }
+}
+
+fn order_canceled_event(order_id: Uuid) -> OrderCanceled {
+    OrderCanceled {
+        event_id: Uuid::new_v4(),
+        order_reference: order_id.to_string(),
+    }
+}
+
+#[async_trait]
+impl ShopModuleEventHandler for ShopEventPublisher {
+    async fn after_order_fulfilled(&self, order: &OrderContext) -> Result<(), ShopError> {
+        let product_type = order.metadata.get(PRODUCT_TYPE_METADATA).cloned();
+        self.order_fulfilled(order.order_id, order.user_id, order.total, product_type).await
+            .map_err(|err| ShopError::HandlerFailed(err.into()))
+    }
+
+    async fn after_order_canceled(&self, order: &OrderContext) -> Result<(), ShopError> {
+        self.order_canceled(order.order_id).await.map_err(|err| ShopError::HandlerFailed(err.into()))
+    }
+
+    async fn after_order_refunded(&self, order: &OrderContext) -> Result<(), ShopError> {
+        self.order_canceled(order.order_id).await.map_err(|err| ShopError::HandlerFailed(err.into()))
+    }
+}
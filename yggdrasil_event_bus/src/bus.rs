@@ -0,0 +1,100 @@
+use crate::error::{BoxError, EventBusError};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+use uuid::Uuid;
+
+pub trait Event: Clone + Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Stable name of the event, used to store it for delayed delivery.
+    const NAME: &'static str;
+
+    /// Unique per occurrence among events of the same [Event::NAME], so consumers can
+    /// recognize an event delivered again. Events of different types may share ids, e.g. one
+    /// caused by the other.
+    fn event_id(&self) -> Uuid;
+}
+
+#[async_trait]
+pub trait EventHandler<E: Event>: Send + Sync {
+    async fn handle(&self, event: &E) -> Result<(), BoxError>;
+}
+
+/// Publishes stored events of one type, found by their [Event::NAME].
+#[async_trait]
+pub(crate) trait StoredEventDecoder: Send + Sync {
+    async fn publish_json(&self, bus: &EventBus, payload: &str) -> Result<(), EventBusError>;
+}
+
+struct TypedDecoder<E>(PhantomData<fn() -> E>);
+
+#[async_trait]
+impl<E: Event> StoredEventDecoder for TypedDecoder<E> {
+    async fn publish_json(&self, bus: &EventBus, payload: &str) -> Result<(), EventBusError> {
+        let event: E = serde_json::from_str(payload)?;
+        bus.publish(&event).await
+    }
+}
+
+type HandlerList<E> = Vec<Arc<dyn EventHandler<E>>>;
+
+/// In-process publish/subscribe of typed events between modules.
+#[derive(Default)]
+pub struct EventBus {
+    /// `HandlerList<E>` by the `TypeId` of `E`.
+    handlers: RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    decoders: RwLock<HashMap<&'static str, Arc<dyn StoredEventDecoder>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe<E: Event>(&self, handler: Arc<dyn EventHandler<E>>) {
+        let mut handlers = self.handlers.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        handlers
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(HandlerList::<E>::new()))
+            .downcast_mut::<HandlerList<E>>()
+            .expect("handler list stored under the TypeId of its event")
+            .push(handler);
+        let mut decoders = self.decoders.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        decoders.entry(E::NAME).or_insert_with(|| Arc::new(TypedDecoder::<E>(PhantomData)));
+    }
+
+    fn handlers_of<E: Event>(&self) -> HandlerList<E> {
+        let handlers = self.handlers.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        handlers
+            .get(&TypeId::of::<E>())
+            .and_then(|list| list.downcast_ref::<HandlerList<E>>())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn decoder_of(&self, name: &str) -> Option<Arc<dyn StoredEventDecoder>> {
+        let decoders = self.decoders.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        decoders.get(name).cloned()
+    }
+
+    /// Run every handler subscribed to `E`, in the order they subscribed.
+    ///
+    /// A failing handler doesn't stop the others, the first failure is returned.
+    pub async fn publish<E: Event>(&self, event: &E) -> Result<(), EventBusError> {
+        let mut first_error = None;
+        for handler in self.handlers_of::<E>() {
+            if let Err(err) = handler.handle(event).await {
+                warn!("Yggdrasil Event Bus: A handler of {} failed: {}", E::NAME, err);
+                first_error.get_or_insert(err);
+            }
+        }
+        match first_error {
+            Some(source) => Err(EventBusError::HandlerFailed { event: E::NAME, source }),
+            None => Ok(()),
+        }
+    }
+}
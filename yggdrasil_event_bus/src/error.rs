@@ -0,0 +1,30 @@
+use sea_orm::DbErr;
+use thiserror::Error;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Error)]
+pub enum EventBusError {
+    #[error("database error")]
+    Database(#[from] DbErr),
+    #[error("failed to serialize or deserialize event")]
+    Serialization(#[from] serde_json::Error),
+    /// The first handler failure, the others are only logged.
+    #[error("handler for {event} failed")]
+    HandlerFailed {
+        event: &'static str,
+        #[source]
+        source: BoxError,
+    },
+}
+
+impl EventBusError {
+    /// Stable identifier for API responses, unaffected by changes to the messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            EventBusError::Database(_) => "event_bus.database",
+            EventBusError::Serialization(_) => "event_bus.serialization",
+            EventBusError::HandlerFailed { .. } => "event_bus.handler_failed",
+        }
+    }
+}
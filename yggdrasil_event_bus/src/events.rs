@@ -0,0 +1,74 @@
+use crate::bus::Event;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// An order was paid and delivered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderFulfilled {
+    /// Unique per fulfillment, so handlers can ignore redeliveries.
+    pub event_id: Uuid,
+    pub order_reference: String,
+    pub buyer_id: Uuid,
+    pub amount: f32,
+    pub product_type: Option<String>,
+}
+
+impl Event for OrderFulfilled {
+    const NAME: &'static str = "shop.order_fulfilled";
+
+    fn event_id(&self) -> Uuid {
+        self.event_id
+    }
+}
+
+/// An order was canceled or refunded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderCanceled {
+    pub event_id: Uuid,
+    pub order_reference: String,
+}
+
+impl Event for OrderCanceled {
+    const NAME: &'static str = "shop.order_canceled";
+
+    fn event_id(&self) -> Uuid {
+        self.event_id
+    }
+}
+
+/// A user registered, with how they reached the site when known.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserRegistered {
+    pub user_id: Uuid,
+    /// Identifies the visitor before registration, e.g. a cookie or device id.
+    pub visitor_key: Option<String>,
+    /// Entered on the registration form.
+    pub referral_code: Option<String>,
+}
+
+impl Event for UserRegistered {
+    const NAME: &'static str = "auth.user_registered";
+
+    /// Users register only once.
+    fn event_id(&self) -> Uuid {
+        self.user_id
+    }
+}
+
+/// An affiliate event was recorded, rewarding the inviter of `invited_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AffiliateRewardRecorded {
+    /// The id of the [AffiliateEvent](yggdrasil_affaliate::event_handler::AffiliateEvent).
+    pub event_id: Uuid,
+    pub inviter_id: Uuid,
+    pub invited_id: Uuid,
+    pub order_reference: Option<String>,
+}
+
+impl Event for AffiliateRewardRecorded {
+    const NAME: &'static str = "affiliate.reward_recorded";
+
+    fn event_id(&self) -> Uuid {
+        self.event_id
+    }
+}
@@ -0,0 +1,7 @@
+pub mod adapters;
+pub mod bus;
+pub mod error;
+pub mod events;
+pub mod outbox;
+pub mod repository;
+pub mod scheduled;
@@ -0,0 +1,178 @@
+use crate::bus::{Event, EventBus, EventHandler};
+use crate::error::{BoxError, EventBusError};
+use crate::repository::{InboxData, OutboxBeforeInsert, OutboxData};
+use async_trait::async_trait;
+use sea_orm::{ConnectionTrait, DatabaseConnection, TransactionTrait};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Queue `event` in the outbox. Call it in the transaction making the change the event
+/// describes, so the event is published if and only if the change is committed.
+///
+/// Returns false if an event of the same type with the same [Event::event_id] is already queued.
+pub async fn enqueue<E: Event>(
+    db: &impl ConnectionTrait,
+    event: &E,
+) -> Result<bool, EventBusError> {
+    Ok(OutboxData::create_if_new(db, OutboxBeforeInsert {
+        event_id: event.event_id(),
+        event_name: E::NAME.to_owned(),
+        payload: serde_json::to_string(event)?,
+    }).await?)
+}
+
+/// Publishes the events queued in the outbox on an [EventBus], at least once each.
+///
+/// Failed deliveries are retried with exponential backoff, and given up after
+/// `max_attempts`. Handlers may see an event again after a failure or crash, wrap them in
+/// [Deduplicated] unless they're idempotent anyway.
+pub struct OutboxRelay {
+    database_connection: Arc<DatabaseConnection>,
+    bus: Arc<EventBus>,
+    batch_size: u64,
+    retry_base: chrono::Duration,
+    max_attempts: i32,
+    lease: chrono::Duration,
+}
+
+impl OutboxRelay {
+    pub fn new(database_connection: Arc<DatabaseConnection>, bus: Arc<EventBus>) -> Self {
+        Self {
+            database_connection,
+            bus,
+            batch_size: 100,
+            retry_base: chrono::Duration::seconds(10),
+            max_attempts: 10,
+            lease: chrono::Duration::minutes(5),
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Delay before the first retry, doubled for every later one.
+    pub fn with_retry_base(mut self, retry_base: chrono::Duration) -> Self {
+        self.retry_base = retry_base;
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: i32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// How long a claimed event is left to this relay before another may deliver it again.
+    /// Should outlast the slowest handler.
+    pub fn with_lease(mut self, lease: chrono::Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    fn next_attempt_at(&self, attempts: i32) -> Option<chrono::NaiveDateTime> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+        let backoff = self.retry_base * 2i32.pow(attempts.clamp(1, 16) as u32 - 1);
+        Some(chrono::Utc::now().naive_utc() + backoff)
+    }
+
+    /// Deliver one batch of due events, returning how many were delivered.
+    ///
+    /// The batch is claimed in a short transaction, then each event is delivered and its
+    /// outcome committed on its own, so handlers don't run inside a long-held transaction.
+    /// Events no decoder is registered for are left queued, and retried once their lease ends.
+    pub async fn relay_once(&self) -> Result<usize, EventBusError> {
+        let db = self.database_connection.as_ref();
+        let now = chrono::Utc::now().naive_utc();
+        let tx = db.begin().await?;
+        let claimed = OutboxData::lock_due(&tx, now, self.batch_size).await?;
+        let ids = claimed.iter().map(|queued| queued.id).collect();
+        OutboxData::lease(&tx, ids, now + self.lease).await?;
+        tx.commit().await?;
+        let mut delivered = 0;
+        for queued in claimed {
+            let Some(decoder) = self.bus.decoder_of(&queued.event_name) else {
+                warn!("Yggdrasil Event Bus: No decoder for event ({}) of {}, left it queued.",
+                    queued.event_id, queued.event_name);
+                continue;
+            };
+            match decoder.publish_json(&self.bus, &queued.payload).await {
+                Ok(()) => {
+                    OutboxData::mark_delivered(db, &queued).await?;
+                    delivered += 1;
+                }
+                Err(err) => {
+                    let next_attempt_at = self.next_attempt_at(queued.attempts + 1);
+                    if next_attempt_at.is_none() {
+                        warn!("Yggdrasil Event Bus: Gave up delivering event ({}) of {} after {} attempts: {}",
+                            queued.event_id, queued.event_name, queued.attempts + 1, err);
+                    }
+                    let message = error_chain(&err);
+                    OutboxData::record_failure(db, &queued, &message, next_attempt_at).await?;
+                }
+            }
+        }
+        Ok(delivered)
+    }
+
+    /// Relay batches forever, waiting `poll_interval` whenever the outbox is drained.
+    pub async fn run(&self, poll_interval: std::time::Duration) {
+        loop {
+            match self.relay_once().await {
+                Ok(delivered) if delivered as u64 >= self.batch_size => continue,
+                Ok(_) => {}
+                Err(err) => warn!("Yggdrasil Event Bus: Relaying the outbox failed: {}", err),
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+}
+
+fn error_chain(err: &dyn std::error::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+        source = err.source();
+    }
+    message
+}
+
+/// Runs `inner` at most once per [Event::NAME] and [Event::event_id] for the `consumer`, recording handled
+/// events in `ygg_event_bus__inbox`.
+///
+/// The event is recorded after `inner` succeeds, so a crash in between still runs it twice.
+pub struct Deduplicated<E, H> {
+    consumer: String,
+    database_connection: Arc<DatabaseConnection>,
+    inner: H,
+    event: PhantomData<fn(&E)>,
+}
+
+impl<E: Event, H: EventHandler<E>> Deduplicated<E, H> {
+    pub fn new(consumer: &str, database_connection: Arc<DatabaseConnection>, inner: H) -> Self {
+        Self {
+            consumer: consumer.to_owned(),
+            database_connection,
+            inner,
+            event: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<E: Event, H: EventHandler<E>> EventHandler<E> for Deduplicated<E, H> {
+    async fn handle(&self, event: &E) -> Result<(), BoxError> {
+        let db = self.database_connection.as_ref();
+        if InboxData::exists(db, &self.consumer, E::NAME, event.event_id()).await? {
+            return Ok(());
+        }
+        self.inner.handle(event).await?;
+        InboxData::create_if_missing(db, &self.consumer, E::NAME, event.event_id()).await?;
+        Ok(())
+    }
+}
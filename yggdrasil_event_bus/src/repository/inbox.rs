@@ -0,0 +1,55 @@
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelBehavior, ActiveValue::Set, ConnectionTrait, DbErr, DeriveEntityModel,
+    DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait,
+};
+use uuid::Uuid;
+
+/// Events a consumer has handled, so redelivered events can be skipped.
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_event_bus__inbox")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub consumer: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub event_name: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub event_id: Uuid,
+    pub processed_at: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type InboxData = Model;
+pub type InboxEntity = Entity;
+
+impl InboxData {
+    pub async fn exists(
+        db: &impl ConnectionTrait,
+        consumer: &str,
+        event_name: &str,
+        event_id: Uuid,
+    ) -> Result<bool, DbErr> {
+        Ok(Entity::find_by_id((consumer.to_owned(), event_name.to_owned(), event_id)).one(db).await?.is_some())
+    }
+
+    pub async fn create_if_missing(
+        db: &impl ConnectionTrait,
+        consumer: &str,
+        event_name: &str,
+        event_id: Uuid,
+    ) -> Result<(), DbErr> {
+        Entity::insert(ActiveModel {
+            consumer: Set(consumer.to_owned()),
+            event_name: Set(event_name.to_owned()),
+            event_id: Set(event_id),
+            processed_at: Set(chrono::Utc::now().naive_utc()),
+        })
+        .on_conflict(OnConflict::columns([Column::Consumer, Column::EventName, Column::EventId]).do_nothing().to_owned())
+        .exec_without_returning(db)
+        .await?;
+        Ok(())
+    }
+}
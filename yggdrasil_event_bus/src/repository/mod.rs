@@ -0,0 +1,5 @@
+mod inbox;
+mod outbox;
+
+pub use inbox::{InboxData, InboxEntity};
+pub use outbox::{OutboxBeforeInsert, OutboxData, OutboxEntity};
@@ -0,0 +1,168 @@
+use sea_orm::{
+    prelude::Expr, sea_query::{LockBehavior, LockType, OnConflict}, ActiveModelBehavior, ActiveModelTrait,
+    ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr, DeleteResult, DeriveEntityModel, DerivePrimaryKey,
+    DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use uuid::Uuid;
+
+/// An event waiting to be published, written in the transaction of the change it describes.
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_event_bus__outbox")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    /// Unique together with `event_name`.
+    pub event_id: Uuid,
+    pub event_name: String,
+    #[sea_orm(column_type = "Text")]
+    pub payload: String,
+    pub attempts: i32,
+    #[sea_orm(index)]
+    pub next_attempt_at: chrono::NaiveDateTime,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub delivered_at: Option<chrono::NaiveDateTime>,
+    /// Set when delivery was given up after too many attempts.
+    pub failed_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type OutboxData = Model;
+pub type OutboxEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxBeforeInsert {
+    pub event_id: Uuid,
+    pub event_name: String,
+    pub payload: String,
+}
+
+impl OutboxData {
+    /// Insert the event unless one with the same `event_name` and `event_id` is already queued.
+    /// Returns whether a row was inserted.
+    pub async fn create_if_new(
+        db: &impl ConnectionTrait,
+        data: OutboxBeforeInsert,
+    ) -> Result<bool, DbErr> {
+        let now = chrono::Utc::now().naive_utc();
+        let result = Entity::insert(ActiveModel {
+            event_id: Set(data.event_id),
+            event_name: Set(data.event_name),
+            payload: Set(data.payload),
+            attempts: Set(0),
+            next_attempt_at: Set(now),
+            created_at: Set(now),
+            ..Default::default()
+        })
+        .on_conflict(OnConflict::columns([Column::EventName, Column::EventId]).do_nothing().to_owned())
+        .exec_without_returning(db)
+        .await?;
+        Ok(result > 0)
+    }
+
+    /// Events due for an attempt at `now`, oldest first, locked until the transaction ends.
+    /// Rows locked by other relays are skipped.
+    pub async fn lock_due(
+        db: &impl ConnectionTrait,
+        now: chrono::NaiveDateTime,
+        limit: u64,
+    ) -> Result<Vec<OutboxData>, DbErr> {
+        Entity::find()
+            .filter(Column::DeliveredAt.is_null())
+            .filter(Column::FailedAt.is_null())
+            .filter(Column::NextAttemptAt.lte(now))
+            .order_by_asc(Column::Id)
+            .limit(limit)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .all(db)
+            .await
+    }
+
+    /// Postpone the next attempt of events `ids` to `until`, so other relays leave them alone
+    /// while they're being delivered.
+    pub async fn lease(
+        db: &impl ConnectionTrait,
+        ids: Vec<i32>,
+        until: chrono::NaiveDateTime,
+    ) -> Result<(), DbErr> {
+        Entity::update_many()
+            .col_expr(Column::NextAttemptAt, Expr::value(until))
+            .filter(Column::Id.is_in(ids))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn mark_delivered(
+        db: &impl ConnectionTrait,
+        before: &OutboxData,
+    ) -> Result<OutboxData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.attempts = Set(before.attempts + 1);
+        active.delivered_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active.last_error = Set(None);
+        active.update(db).await
+    }
+
+    /// Count a failed attempt, retrying at `next_attempt_at` or giving up when it's `None`.
+    pub async fn record_failure(
+        db: &impl ConnectionTrait,
+        before: &OutboxData,
+        error: &str,
+        next_attempt_at: Option<chrono::NaiveDateTime>,
+    ) -> Result<OutboxData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.attempts = Set(before.attempts + 1);
+        active.last_error = Set(Some(error.to_owned()));
+        match next_attempt_at {
+            Some(next_attempt_at) => active.next_attempt_at = Set(next_attempt_at),
+            None => active.failed_at = Set(Some(chrono::Utc::now().naive_utc())),
+        }
+        active.update(db).await
+    }
+
+    /// Queue an event that was given up on again.
+    pub async fn retry_failed(
+        db: &impl ConnectionTrait,
+        id: i32,
+    ) -> Result<(), DbErr> {
+        Entity::update_many()
+            .col_expr(Column::FailedAt, Expr::value(Option::<chrono::NaiveDateTime>::None))
+            .col_expr(Column::NextAttemptAt, Expr::value(chrono::Utc::now().naive_utc()))
+            .filter(Column::Id.eq(id))
+            .filter(Column::FailedAt.is_not_null())
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn find_failed(
+        db: &impl ConnectionTrait,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<OutboxData>, DbErr> {
+        Entity::find()
+            .filter(Column::FailedAt.is_not_null())
+            .order_by_asc(Column::Id)
+            .offset(offset)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// Remove events delivered before `before`.
+    pub async fn delete_delivered(
+        db: &impl ConnectionTrait,
+        before: chrono::NaiveDateTime,
+    ) -> Result<DeleteResult, DbErr> {
+        Entity::delete_many()
+            .filter(Column::DeliveredAt.lt(before))
+            .exec(db)
+            .await
+    }
+}
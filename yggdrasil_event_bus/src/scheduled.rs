@@ -0,0 +1,67 @@
+use crate::bus::{Event, EventBus};
+use crate::error::EventBusError;
+use sea_orm::ConnectionTrait;
+use tracing::warn;
+use yggdrasil_schedule::repository::{ScheduledEventBeforeInsert, ScheduledEventData};
+
+/// Prefix of the `consumer` of scheduled events published through the bus.
+const CONSUMER_PREFIX: &str = "event_bus:";
+
+/// How long a claimed event is left to one worker. Events of a worker that crashed while
+/// delivering them are due again afterwards.
+const CLAIM_LEASE_MINUTES: i64 = 5;
+
+impl EventBus {
+    /// Store `event` in `ygg_schedule__scheduled_event` to be published at `time` by
+    /// [EventBus::deliver_due].
+    pub async fn publish_at<E: Event>(
+        &self,
+        db: &impl ConnectionTrait,
+        event: &E,
+        time: chrono::NaiveDateTime,
+    ) -> Result<ScheduledEventData, EventBusError> {
+        Ok(ScheduledEventData::create(db, ScheduledEventBeforeInsert {
+            time,
+            payload: serde_json::to_string(event)?,
+            consumer: format!("{}{}", CONSUMER_PREFIX, E::NAME),
+        }).await?)
+    }
+
+    /// Publish up to `limit` scheduled events that are due, returning how many were delivered.
+    ///
+    /// Events are claimed before publishing so several workers can poll at once, and marked
+    /// executed only once delivered. Events whose handlers fail are released to be retried by
+    /// the next call, and events without a subscriber on this bus are left for a bus that
+    /// has one.
+    pub async fn deliver_due(
+        &self,
+        db: &impl ConnectionTrait,
+        limit: u64,
+    ) -> Result<usize, EventBusError> {
+        let now = chrono::Utc::now().naive_utc();
+        let due = ScheduledEventData::get_due_by_consumer_prefix(db, CONSUMER_PREFIX, now, limit).await?;
+        let mut delivered = 0;
+        for scheduled in due {
+            let name = &scheduled.consumer[CONSUMER_PREFIX.len()..];
+            let Some(decoder) = self.decoder_of(name) else {
+                continue;
+            };
+            let claimed_until = now + chrono::Duration::minutes(CLAIM_LEASE_MINUTES);
+            if !ScheduledEventData::try_claim(db, scheduled.id, now, claimed_until).await? {
+                continue;
+            }
+            match decoder.publish_json(self, &scheduled.payload).await {
+                Ok(()) => {
+                    ScheduledEventData::set_have_been_executed(db, &scheduled, true).await?;
+                    delivered += 1;
+                }
+                Err(err) => {
+                    warn!("Yggdrasil Event Bus: Scheduled event ({}) of {} failed and will be retried: {}",
+                        scheduled.id, name, err);
+                    ScheduledEventData::release(db, scheduled.id).await?;
+                }
+            }
+        }
+        Ok(delivered)
+    }
+}
@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DatabaseConnection, Statement};
+use sea_orm_migration::MigrationStatus;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Where the per-module CLIs recorded the migrations of every module, before each module
+/// got its own history table.
+const LEGACY_HISTORY_TABLE: &str = "seaql_migrations";
+
+/// The migrations of one module, tracked in its own `ygg_<module>__seaql_migrations` table.
+#[async_trait]
+pub trait ModuleMigrator: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// The table the module records its applied migrations in.
+    fn history_table(&self) -> String;
+    fn migration_names(&self) -> Vec<String>;
+    /// Create the history table if it's missing.
+    async fn install(&self, db: &DatabaseConnection) -> Result<(), DbErr>;
+    async fn up(&self, db: &DatabaseConnection) -> Result<(), DbErr>;
+    /// Roll back the last `steps` migrations, or all of them.
+    async fn down(&self, db: &DatabaseConnection, steps: Option<u32>) -> Result<(), DbErr>;
+    async fn status(&self, db: &DatabaseConnection) -> Result<Vec<MigrationState>, DbErr>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationState {
+    pub module: &'static str,
+    pub migration: String,
+    pub applied: bool,
+}
+
+struct Module<M> {
+    name: &'static str,
+    migrator: PhantomData<fn() -> M>,
+}
+
+/// Wrap the migrator of a module, e.g. to run an application's own migrations after
+/// [modules].
+pub fn module<M: MigratorTrait + 'static>(name: &'static str) -> Box<dyn ModuleMigrator> {
+    Box::new(Module::<M> {
+        name,
+        migrator: PhantomData,
+    })
+}
+
+#[async_trait]
+impl<M: MigratorTrait> ModuleMigrator for Module<M> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn history_table(&self) -> String {
+        M::migration_table_name().to_string()
+    }
+
+    fn migration_names(&self) -> Vec<String> {
+        M::migrations().iter().map(|migration| migration.name().to_owned()).collect()
+    }
+
+    async fn install(&self, db: &DatabaseConnection) -> Result<(), DbErr> {
+        M::install(db).await
+    }
+
+    async fn up(&self, db: &DatabaseConnection) -> Result<(), DbErr> {
+        M::up(db, None).await
+    }
+
+    async fn down(&self, db: &DatabaseConnection, steps: Option<u32>) -> Result<(), DbErr> {
+        M::down(db, steps).await
+    }
+
+    async fn status(&self, db: &DatabaseConnection) -> Result<Vec<MigrationState>, DbErr> {
+        Ok(M::get_migration_with_status(db).await?.into_iter().map(|migration| MigrationState {
+            module: self.name,
+            migration: migration.name().to_owned(),
+            applied: migration.status() == MigrationStatus::Applied,
+        }).collect())
+    }
+}
+
+/// The enabled modules, each after the modules it depends on.
+pub fn modules() -> Vec<Box<dyn ModuleMigrator>> {
+    vec![
+        #[cfg(feature = "user")]
+        module::<user_migration::Migrator>("user"),
+        #[cfg(feature = "auth")]
+        module::<auth_migration::Migrator>("auth"),
+        #[cfg(feature = "schedule")]
+        module::<schedule_migration::Migrator>("schedule"),
+        #[cfg(feature = "shop")]
+        module::<shop_migration::Migrator>("shop"),
+        #[cfg(feature = "affiliate")]
+        module::<affiliate_migration::Migrator>("affiliate"),
+        #[cfg(feature = "event_bus")]
+        module::<event_bus_migration::Migrator>("event_bus"),
+    ]
+}
+
+/// Seed the history of every enabled module which has none yet from the legacy
+/// `seaql_migrations` table, returning how many migrations were carried over.
+///
+/// Names used by several modules, like the `m20220101_000001_create_table` baselines, can't be
+/// attributed to one of them and aren't carried over. Those migrations run again and only
+/// create what's missing.
+pub async fn adopt_legacy_history(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    if !SchemaManager::new(db).has_table(LEGACY_HISTORY_TABLE).await? {
+        return Ok(0);
+    }
+    let modules = modules();
+    let mut modules_by_name: HashMap<String, usize> = HashMap::new();
+    for module in &modules {
+        for name in module.migration_names() {
+            *modules_by_name.entry(name).or_default() += 1;
+        }
+    }
+    let backend = db.get_database_backend();
+    let mut adopted = 0;
+    for module in &modules {
+        module.install(db).await?;
+        let history_table = module.history_table();
+        let has_history = db.query_one(Statement::from_string(backend,
+            format!(r#"SELECT 1 FROM "{}" LIMIT 1"#, history_table))).await?.is_some();
+        if has_history {
+            continue;
+        }
+        for name in module.migration_names() {
+            if modules_by_name[&name] > 1 {
+                continue;
+            }
+            let result = db.execute(Statement::from_sql_and_values(backend, format!(
+                r#"INSERT INTO "{}" (version, applied_at) SELECT version, applied_at FROM "{}" WHERE version = $1"#,
+                history_table, LEGACY_HISTORY_TABLE,
+            ), [name.into()])).await?;
+            adopted += result.rows_affected();
+        }
+    }
+    Ok(adopted)
+}
+
+/// Apply the pending migrations of every enabled module, after adopting their history from
+/// the legacy `seaql_migrations` table on the first run.
+pub async fn up(db: &DatabaseConnection) -> Result<(), DbErr> {
+    adopt_legacy_history(db).await?;
+    for module in modules() {
+        module.up(db).await?;
+    }
+    Ok(())
+}
+
+/// Roll back the last `steps` migrations of `module`, or all of them.
+pub async fn down(db: &DatabaseConnection, module: &str, steps: Option<u32>) -> Result<(), DbErr> {
+    let module = modules().into_iter().find(|it| it.name() == module)
+        .ok_or_else(|| DbErr::Custom(format!("module {} is not enabled", module)))?;
+    module.down(db, steps).await
+}
+
+/// Roll back every enabled module, dependents first.
+pub async fn reset(db: &DatabaseConnection) -> Result<(), DbErr> {
+    for module in modules().into_iter().rev() {
+        module.down(db, None).await?;
+    }
+    Ok(())
+}
+
+pub async fn status(db: &DatabaseConnection) -> Result<Vec<MigrationState>, DbErr> {
+    let mut states = Vec::new();
+    for module in modules() {
+        states.extend(module.status(db).await?);
+    }
+    Ok(states)
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::sea_orm::Database;
+use sea_orm_migration::DbErr;
+
+const USAGE: &str = "usage: yggdrasil_migration [up | down <module> [-n <steps>] | reset | refresh | status]";
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(err) = run(&args).await {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: &[String]) -> Result<(), DbErr> {
+    let url = std::env::var("DATABASE_URL")
+        .map_err(|_| DbErr::Custom("DATABASE_URL must be set".to_owned()))?;
+    let db = Database::connect(&url).await?;
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] | ["up"] => yggdrasil_migration::up(&db).await,
+        ["down", module] => yggdrasil_migration::down(&db, module, Some(1)).await,
+        ["down", module, "-n", steps] => {
+            let steps = steps.parse().map_err(|_| DbErr::Custom(USAGE.to_owned()))?;
+            yggdrasil_migration::down(&db, module, Some(steps)).await
+        }
+        ["reset"] => yggdrasil_migration::reset(&db).await,
+        ["refresh"] => {
+            yggdrasil_migration::reset(&db).await?;
+            yggdrasil_migration::up(&db).await
+        }
+        ["status"] => {
+            for state in yggdrasil_migration::status(&db).await? {
+                let status = if state.applied { "applied" } else { "pending" };
+                println!("{}: {} ... {}", state.module, state.migration, status);
+            }
+            Ok(())
+        }
+        _ => Err(DbErr::Custom(USAGE.to_owned())),
+    }
+}
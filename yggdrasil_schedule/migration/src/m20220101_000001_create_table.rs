@@ -1,4 +1,4 @@
-use sea_orm_migration::{prelude::*, schema::*};
+use sea_orm_migration::prelude::*;
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -32,6 +32,7 @@ impl MigrationTrait for Migration {
         ).await?;
         manager.create_index(
             Index::create()
+                .if_not_exists()
                 .table(ScheduledEvent::Table)
                 .name("ygg_schedule__scheduled_event_time_index")
                 .col(ScheduledEvent::Time)
@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum ScheduledEvent {
+    #[sea_orm(iden = "ygg_schedule__scheduled_event")]
+    Table,
+    ClaimedUntil,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(ScheduledEvent::Table)
+                .add_column(ColumnDef::new(ScheduledEvent::ClaimedUntil).timestamp().null())
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter()
+                .table(ScheduledEvent::Table)
+                .drop_column(ScheduledEvent::ClaimedUntil)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+}
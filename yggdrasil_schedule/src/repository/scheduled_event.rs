@@ -1,23 +1,27 @@
-use chrono::NaiveTime;
+use chrono::NaiveDateTime;
 use sea_orm::{
-    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    prelude::Expr, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition, ConnectionTrait, DbErr,
     DeleteResult, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter,
-    PrimaryKeyTrait, QueryFilter,
+    PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect,
 };
 
 #[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "ygg_schedule__scheduled_event")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u64,
+    pub id: i32,
+    /// When the event is due.
     #[sea_orm(index)]
-    pub time: NaiveTime,
+    pub time: NaiveDateTime,
     #[sea_orm(column_type = "Text")]
     pub payload: String,
     #[sea_orm(default=false)]
     pub have_been_executed: bool,
     pub consumer: String,
-    pub created_at: NaiveTime,
+    pub created_at: NaiveDateTime,
+    /// Set while a worker delivers the event. Claims older than this are considered stale,
+    /// e.g. after a crash, and the event becomes due again.
+    pub claimed_until: Option<NaiveDateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -29,7 +33,7 @@ pub type ScheduledEventData = Model;
 pub type ScheduledEventEntity = Entity;
 
 pub struct ScheduledEventBeforeInsert {
-    pub time: NaiveTime,
+    pub time: NaiveDateTime,
     pub payload: String,
     pub consumer: String,
 }
@@ -43,7 +47,7 @@ impl ScheduledEventData {
             time: Set(data.time),
             payload: Set(data.payload),
             consumer: Set(data.consumer),
-            created_at: Set(chrono::Utc::now().time()),
+            created_at: Set(chrono::Utc::now().naive_utc()),
             ..Default::default()
         }
         .insert(db)
@@ -60,27 +64,85 @@ impl ScheduledEventData {
         active.update(db).await
     }
 
+    /// Claim the event until `until`, unless it was executed or another worker holds an
+    /// unexpired claim at `now`. Returns whether this call claimed it.
+    ///
+    /// Mark the event executed once it's delivered, or [release](Self::release) it.
+    pub async fn try_claim(
+        db: &impl ConnectionTrait,
+        id: i32,
+        now: NaiveDateTime,
+        until: NaiveDateTime,
+    ) -> Result<bool, DbErr> {
+        let result = ScheduledEventEntity::update_many()
+            .col_expr(Column::ClaimedUntil, Expr::value(until))
+            .filter(Column::Id.eq(id))
+            .filter(Column::HaveBeenExecuted.eq(false))
+            .filter(Self::unclaimed_at(now))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Drop the claim on the event, so it's due again right away.
+    pub async fn release(
+        db: &impl ConnectionTrait,
+        id: i32,
+    ) -> Result<(), DbErr> {
+        ScheduledEventEntity::update_many()
+            .col_expr(Column::ClaimedUntil, Expr::value(Option::<NaiveDateTime>::None))
+            .filter(Column::Id.eq(id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    fn unclaimed_at(now: NaiveDateTime) -> Condition {
+        Condition::any()
+            .add(Column::ClaimedUntil.is_null())
+            .add(Column::ClaimedUntil.lte(now))
+    }
+
     pub async fn delete_by_id(
         db: &impl ConnectionTrait,
-        id: u64,
+        id: i32,
     ) -> Result<DeleteResult, DbErr> {
         ScheduledEventEntity::delete_by_id(id).exec(db).await
     }
 
     pub async fn find_by_id(
         db: &impl ConnectionTrait,
-        id: u64,
+        id: i32,
     ) -> Result<Option<ScheduledEventData>, DbErr> {
         ScheduledEventEntity::find_by_id(id).one(db).await
     }
 
     pub async fn get_all_on_time(
         db: &impl ConnectionTrait,
-        time: NaiveTime,
+        time: NaiveDateTime,
+    ) -> Result<Vec<ScheduledEventData>, DbErr> {
+        ScheduledEventEntity::find()
+            .filter(Column::Time.lte(time))
+            .filter(Column::HaveBeenExecuted.eq(false))
+            .all(db)
+            .await
+    }
+
+    /// Unexecuted and unclaimed events of consumers starting with `consumer_prefix` due at
+    /// `time`, earliest first.
+    pub async fn get_due_by_consumer_prefix(
+        db: &impl ConnectionTrait,
+        consumer_prefix: &str,
+        time: NaiveDateTime,
+        limit: u64,
     ) -> Result<Vec<ScheduledEventData>, DbErr> {
         ScheduledEventEntity::find()
             .filter(Column::Time.lte(time))
             .filter(Column::HaveBeenExecuted.eq(false))
+            .filter(Column::Consumer.starts_with(consumer_prefix))
+            .filter(Self::unclaimed_at(time))
+            .order_by_asc(Column::Time)
+            .limit(limit)
             .all(db)
             .await
     }
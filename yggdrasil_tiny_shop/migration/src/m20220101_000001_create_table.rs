@@ -1,9 +1,10 @@
-use sea_orm_migration::{prelude::*, schema::*};
+use sea_orm_migration::prelude::*;
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
 
 #[derive(DeriveIden)]
+#[allow(clippy::enum_variant_names)]
 enum Production {
     #[sea_orm(iden = "ygg_tiny_shop__production")]
     Table,
@@ -40,6 +41,7 @@ impl MigrationTrait for Migration {
         ).await?;
         manager.create_index(
             Index::create()
+                .if_not_exists()
                 .table(Production::Table)
                 .name("ygg_tiny_shop__production_name_index")
                 .col(Production::Name)
@@ -47,6 +49,7 @@ impl MigrationTrait for Migration {
         ).await?;
         manager.create_index(
             Index::create()
+                .if_not_exists()
                 .table(Production::Table)
                 .name("ygg_tiny_shop__production_production_type_index")
                 .col(Production::ProductionType)
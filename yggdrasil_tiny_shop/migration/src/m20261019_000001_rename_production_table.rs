@@ -0,0 +1,31 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const OLD_TABLE: &str = "ygg_tiny_shop__production";
+const NEW_TABLE: &str = "ygg_shop__production";
+
+/// The baseline created the production table under the crate's old prefix, while the entity
+/// reads `ygg_shop__production`. Databases already on the new name are left alone.
+async fn rename(manager: &SchemaManager<'_>, from: &str, to: &str) -> Result<(), DbErr> {
+    if !manager.has_table(from).await? || manager.has_table(to).await? {
+        return Ok(());
+    }
+    manager.rename_table(
+        Table::rename()
+            .table(Alias::new(from), Alias::new(to))
+            .to_owned()
+    ).await
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        rename(manager, OLD_TABLE, NEW_TABLE).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        rename(manager, NEW_TABLE, OLD_TABLE).await
+    }
+}
@@ -0,0 +1,47 @@
+use sea_orm::DbErr;
+use thiserror::Error;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Error)]
+pub enum ShopError {
+    #[error("database error")]
+    Database(#[from] DbErr),
+    #[error("production {0} not found")]
+    ProductionNotFound(i32),
+    #[error("stock amount must be positive, got {0}")]
+    InvalidAmount(i32),
+    #[error("production {production_id} has {available} in stock, {requested} requested")]
+    InsufficientStock {
+        production_id: i32,
+        requested: i32,
+        available: i32,
+    },
+    /// More stock would be released than is locked.
+    #[error("production {production_id} has {locked} locked, {requested} requested")]
+    InsufficientLockedStock {
+        production_id: i32,
+        requested: i32,
+        locked: i32,
+    },
+    #[error("order rejected: {0}")]
+    OrderRejected(String),
+    /// An event handler failed for a reason of its own.
+    #[error("event handler failed")]
+    HandlerFailed(#[source] BoxError),
+}
+
+impl ShopError {
+    /// Stable identifier for API responses, unaffected by changes to the messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ShopError::Database(_) => "shop.database",
+            ShopError::ProductionNotFound(_) => "shop.production_not_found",
+            ShopError::InvalidAmount(_) => "shop.invalid_amount",
+            ShopError::InsufficientStock { .. } => "shop.insufficient_stock",
+            ShopError::InsufficientLockedStock { .. } => "shop.insufficient_locked_stock",
+            ShopError::OrderRejected(_) => "shop.order_rejected",
+            ShopError::HandlerFailed(_) => "shop.handler_failed",
+        }
+    }
+}
@@ -1,6 +1,11 @@
+use crate::error::ShopError;
+use crate::repository::ProductionData;
 use sea_orm::sqlx::types::chrono;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CartItem {
@@ -15,9 +20,152 @@ pub struct Cart {
     pub create_at: chrono::NaiveDateTime,
 }
 
+/// The order a hook is called for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderContext {
+    pub order_id: Uuid,
+    /// The buyer.
+    pub user_id: Uuid,
+    pub cart: Cart,
+    /// Sum of the item prices.
+    pub subtotal: f32,
+    /// Amount charged, which handlers may adjust before the order is created, e.g. for discounts.
+    pub total: f32,
+    /// Free-form values handlers attach for each other and for the caller.
+    pub metadata: HashMap<String, String>,
+}
+
+impl OrderContext {
+    pub fn new(order_id: Uuid, user_id: Uuid, cart: Cart, subtotal: f32) -> Self {
+        Self {
+            order_id,
+            user_id,
+            cart,
+            subtotal,
+            total: subtotal,
+            metadata: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderDecision {
+    Proceed,
+    /// Don't create the order, for the reason given to the buyer.
+    Reject(String),
+}
+
+/// Hooks into the order lifecycle. Every hook does nothing by default, so handlers only
+/// implement the ones they need.
 #[async_trait::async_trait]
-pub trait ShopModuleEventHandler {
-    async fn before_order_created(&self, cart: Cart);
-    async fn after_order_fulfilled(&self, cart: Cart);
-    async fn after_order_canceled(&self, cart: Cart);
-}
\ No newline at end of file
+pub trait ShopModuleEventHandler: Send + Sync {
+    /// Called before the order is stored. Handlers may adjust `order`, or reject it.
+    async fn before_order_created(&self, _order: &mut OrderContext) -> Result<OrderDecision, ShopError> {
+        Ok(OrderDecision::Proceed)
+    }
+
+    async fn after_payment_succeeded(&self, _order: &OrderContext) -> Result<(), ShopError> {
+        Ok(())
+    }
+
+    async fn after_order_fulfilled(&self, _order: &OrderContext) -> Result<(), ShopError> {
+        Ok(())
+    }
+
+    async fn after_order_canceled(&self, _order: &OrderContext) -> Result<(), ShopError> {
+        Ok(())
+    }
+
+    async fn after_order_refunded(&self, _order: &OrderContext) -> Result<(), ShopError> {
+        Ok(())
+    }
+
+    /// Called when the stock of `production` dropped to the threshold given to
+    /// [ShopEventHandlers::check_stock] or below.
+    async fn on_stock_low(&self, _production: &ProductionData) -> Result<(), ShopError> {
+        Ok(())
+    }
+}
+
+/// The registered handlers, called in registration order.
+#[derive(Clone, Default)]
+pub struct ShopEventHandlers {
+    handlers: Vec<Arc<dyn ShopModuleEventHandler>>,
+}
+
+impl ShopEventHandlers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, handler: Arc<dyn ShopModuleEventHandler>) -> &mut Self {
+        self.handlers.push(handler);
+        self
+    }
+
+    /// Run the handlers until one rejects the order or fails, so later handlers only see
+    /// orders that will be created.
+    pub async fn before_order_created(&self, order: &mut OrderContext) -> Result<(), ShopError> {
+        for handler in &self.handlers {
+            if let OrderDecision::Reject(reason) = handler.before_order_created(order).await? {
+                return Err(ShopError::OrderRejected(reason));
+            }
+        }
+        Ok(())
+    }
+
+    /// Run every handler, even after one fails, and return the first error.
+    pub async fn after_payment_succeeded(&self, order: &OrderContext) -> Result<(), ShopError> {
+        let mut result = Ok(());
+        for handler in &self.handlers {
+            keep_first_error(&mut result, handler.after_payment_succeeded(order).await);
+        }
+        result
+    }
+
+    /// Run every handler, even after one fails, and return the first error.
+    pub async fn after_order_fulfilled(&self, order: &OrderContext) -> Result<(), ShopError> {
+        let mut result = Ok(());
+        for handler in &self.handlers {
+            keep_first_error(&mut result, handler.after_order_fulfilled(order).await);
+        }
+        result
+    }
+
+    /// Run every handler, even after one fails, and return the first error.
+    pub async fn after_order_canceled(&self, order: &OrderContext) -> Result<(), ShopError> {
+        let mut result = Ok(());
+        for handler in &self.handlers {
+            keep_first_error(&mut result, handler.after_order_canceled(order).await);
+        }
+        result
+    }
+
+    /// Run every handler, even after one fails, and return the first error.
+    pub async fn after_order_refunded(&self, order: &OrderContext) -> Result<(), ShopError> {
+        let mut result = Ok(());
+        for handler in &self.handlers {
+            keep_first_error(&mut result, handler.after_order_refunded(order).await);
+        }
+        result
+    }
+
+    /// Call [ShopModuleEventHandler::on_stock_low] if `production` has `threshold` or less in
+    /// stock, e.g. after [ProductionData::lock_stock].
+    pub async fn check_stock(&self, production: &ProductionData, threshold: i32) -> Result<(), ShopError> {
+        if production.infinity_stock || production.stock > threshold {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        for handler in &self.handlers {
+            keep_first_error(&mut result, handler.on_stock_low(production).await);
+        }
+        result
+    }
+}
+
+fn keep_first_error(result: &mut Result<(), ShopError>, next: Result<(), ShopError>) {
+    if let (Ok(()), Err(err)) = (&result, next) {
+        *result = Err(err);
+    }
+}
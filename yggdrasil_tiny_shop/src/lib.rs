@@ -1,3 +1,4 @@
 pub mod repository;
 pub mod event_handler;
+pub mod error;
 
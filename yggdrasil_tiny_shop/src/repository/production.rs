@@ -1,6 +1,7 @@
+use crate::error::ShopError;
 use sea_orm::entity::prelude::*;
 use sea_orm::ActiveValue::Set;
-use sea_orm::QuerySelect;
+use sea_orm::{Condition, QuerySelect};
 use std::default::Default;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
@@ -133,29 +134,71 @@ impl ProductionData {
         }.insert(db).await
     }
 
+    /// Move `change_amount` from stock to locked stock, failing if not enough is in stock.
+    ///
+    /// Checked and moved in a single statement, so concurrent orders can't oversell.
     pub async fn lock_stock(
         db: &impl ConnectionTrait,
         before: &ProductionData,
         change_amount: i32,
-    ) -> Result<ProductionData, DbErr> {
-        let current_stock = before.stock;
-        let current_locked_stock = before.locked_stock;
-        let mut active: ActiveModel = before.clone().into();
-        active.stock = Set(current_stock - change_amount);
-        active.locked_stock = Set(current_locked_stock + change_amount);
-        active.update(db).await
+    ) -> Result<ProductionData, ShopError> {
+        if change_amount <= 0 {
+            return Err(ShopError::InvalidAmount(change_amount));
+        }
+        let updated = Entity::update_many()
+            .col_expr(Column::Stock, Expr::col(Column::Stock).sub(change_amount))
+            .col_expr(Column::LockedStock, Expr::col(Column::LockedStock).add(change_amount))
+            .filter(Column::Id.eq(before.id))
+            .filter(
+                Condition::any()
+                    .add(Column::InfinityStock.eq(true))
+                    .add(Column::Stock.gte(change_amount)),
+            )
+            .exec_with_returning(db)
+            .await?;
+        if let Some(production) = updated.into_iter().next() {
+            return Ok(production);
+        }
+        let current = Self::find_current(db, before.id).await?;
+        Err(ShopError::InsufficientStock {
+            production_id: current.id,
+            requested: change_amount,
+            available: current.stock,
+        })
     }
 
+    /// Return `change_amount` from locked stock to stock, e.g. when an order is canceled.
     pub async fn unlock_stock(
         db: &impl ConnectionTrait,
         before: &ProductionData,
         change_amount: i32,
-    ) -> Result<ProductionData, DbErr> {
-        let current_stock = before.stock;
-        let current_locked_stock = before.locked_stock;
-        let mut active: ActiveModel = before.clone().into();
-        active.stock = Set(current_stock + change_amount);
-        active.locked_stock = Set(current_locked_stock - change_amount);
-        active.update(db).await
+    ) -> Result<ProductionData, ShopError> {
+        if change_amount <= 0 {
+            return Err(ShopError::InvalidAmount(change_amount));
+        }
+        let updated = Entity::update_many()
+            .col_expr(Column::Stock, Expr::col(Column::Stock).add(change_amount))
+            .col_expr(Column::LockedStock, Expr::col(Column::LockedStock).sub(change_amount))
+            .filter(Column::Id.eq(before.id))
+            .filter(Column::LockedStock.gte(change_amount))
+            .exec_with_returning(db)
+            .await?;
+        if let Some(production) = updated.into_iter().next() {
+            return Ok(production);
+        }
+        let current = Self::find_current(db, before.id).await?;
+        Err(ShopError::InsufficientLockedStock {
+            production_id: current.id,
+            requested: change_amount,
+            locked: current.locked_stock,
+        })
+    }
+
+    /// Reload production `id` after a conditional update matched nothing.
+    async fn find_current(db: &impl ConnectionTrait, id: i32) -> Result<ProductionData, ShopError> {
+        Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or(ShopError::ProductionNotFound(id))
     }
 }
@@ -0,0 +1,20 @@
+pub use sea_orm_migration::prelude::*;
+
+mod m20261019_000001_create_table;
+mod m20261019_000002_add_service_account;
+
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl MigratorTrait for Migrator {
+    fn migration_table_name() -> DynIden {
+        Alias::new("ygg_user__seaql_migrations").into_iden()
+    }
+
+    fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+        vec![
+            Box::new(m20261019_000001_create_table::Migration),
+            Box::new(m20261019_000002_add_service_account::Migration),
+        ]
+    }
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum User {
+    #[sea_orm(iden = "ygg_user__user")]
+    Table,
+    Id,
+    DisplayName,
+    Status,
+    CreatedAt,
+    LastLoginAt,
+    DeletedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.create_table(
+            Table::create()
+                .table(User::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(User::Id).uuid().not_null().primary_key())
+                .col(ColumnDef::new(User::DisplayName).string().not_null())
+                .col(ColumnDef::new(User::Status).string_len(16).not_null().default("active"))
+                .col(ColumnDef::new(User::CreatedAt).timestamp().not_null())
+                .col(ColumnDef::new(User::LastLoginAt).timestamp().null())
+                .col(ColumnDef::new(User::DeletedAt).timestamp().null())
+                .to_owned()
+        ).await?;
+        manager.create_index(
+            Index::create()
+                .table(User::Table)
+                .name("ygg_user__user_status_index")
+                .col(User::Status)
+                .to_owned()
+        ).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_index(
+            Index::drop()
+                .name("ygg_user__user_status_index")
+                .table(User::Table)
+                .to_owned()
+        ).await?;
+        manager.drop_table(Table::drop().table(User::Table).to_owned()).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,3 @@
+mod user;
+
+pub use user::{UserBeforeInsert, UserData, UserEntity, UserStatus};
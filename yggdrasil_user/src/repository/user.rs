@@ -0,0 +1,178 @@
+use sea_orm::{
+    sea_query::StringLen, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
+    ConnectionTrait, DbErr, DeriveActiveEnum, DeriveEntityModel, DerivePrimaryKey, DeriveRelation,
+    EntityTrait, EnumIter, PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+pub enum UserStatus {
+    #[sea_orm(string_value = "active")]
+    Active,
+    #[sea_orm(string_value = "suspended")]
+    Suspended,
+    #[sea_orm(string_value = "deleted")]
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "ygg_user__user")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+
+    pub display_name: String,
+
+    #[sea_orm(index)]
+    pub status: UserStatus,
+
+    /// Machines acting through API keys rather than people.
+    #[sea_orm(default_value = false)]
+    pub is_service_account: bool,
+
+    pub created_at: chrono::NaiveDateTime,
+    pub last_login_at: Option<chrono::NaiveDateTime>,
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub type UserData = Model;
+pub type UserEntity = Entity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserBeforeInsert {
+    pub display_name: String,
+    pub is_service_account: bool,
+}
+
+impl UserData {
+    /// Whether the user may sign in and be referenced by other modules.
+    pub fn is_active(&self) -> bool {
+        self.status == UserStatus::Active && self.deleted_at.is_none()
+    }
+
+    pub async fn create(
+        db: &impl ConnectionTrait,
+        data: UserBeforeInsert,
+    ) -> Result<UserData, DbErr> {
+        ActiveModel {
+            id: Set(Uuid::new_v4()),
+            display_name: Set(data.display_name),
+            status: Set(UserStatus::Active),
+            is_service_account: Set(data.is_service_account),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            last_login_at: Set(None),
+            deleted_at: Set(None),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Find a user which has not been soft deleted.
+    pub async fn find_by_id(
+        db: &impl ConnectionTrait,
+        id: Uuid,
+    ) -> Result<Option<UserData>, DbErr> {
+        Entity::find_by_id(id)
+            .filter(Column::DeletedAt.is_null())
+            .one(db)
+            .await
+    }
+
+    /// Find a user, including soft deleted ones.
+    pub async fn find_by_id_with_deleted(
+        db: &impl ConnectionTrait,
+        id: Uuid,
+    ) -> Result<Option<UserData>, DbErr> {
+        Entity::find_by_id(id).one(db).await
+    }
+
+    /// Find several users at once, skipping unknown and soft deleted ones.
+    pub async fn find_by_ids(
+        db: &impl ConnectionTrait,
+        ids: Vec<Uuid>,
+    ) -> Result<Vec<UserData>, DbErr> {
+        Entity::find()
+            .filter(Column::Id.is_in(ids))
+            .filter(Column::DeletedAt.is_null())
+            .all(db)
+            .await
+    }
+
+    pub async fn list(
+        db: &impl ConnectionTrait,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<UserData>, DbErr> {
+        Entity::find()
+            .filter(Column::DeletedAt.is_null())
+            .order_by_asc(Column::CreatedAt)
+            .offset(offset)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    pub async fn list_service_accounts(db: &impl ConnectionTrait) -> Result<Vec<UserData>, DbErr> {
+        Entity::find()
+            .filter(Column::IsServiceAccount.eq(true))
+            .filter(Column::DeletedAt.is_null())
+            .order_by_asc(Column::CreatedAt)
+            .all(db)
+            .await
+    }
+
+    pub async fn update_display_name(
+        db: &impl ConnectionTrait,
+        before: &UserData,
+        new_display_name: &str,
+    ) -> Result<UserData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.display_name = Set(new_display_name.to_owned());
+        active.update(db).await
+    }
+
+    pub async fn update_status(
+        db: &impl ConnectionTrait,
+        before: &UserData,
+        new_status: UserStatus,
+    ) -> Result<UserData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.status = Set(new_status);
+        active.update(db).await
+    }
+
+    pub async fn touch_last_login(
+        db: &impl ConnectionTrait,
+        before: &UserData,
+    ) -> Result<UserData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.last_login_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active.update(db).await
+    }
+
+    /// Mark the user as deleted while keeping the row, so references from other modules
+    /// stay valid.
+    pub async fn soft_delete(
+        db: &impl ConnectionTrait,
+        before: &UserData,
+    ) -> Result<UserData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.status = Set(UserStatus::Deleted);
+        active.deleted_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active.update(db).await
+    }
+
+    pub async fn restore(db: &impl ConnectionTrait, before: &UserData) -> Result<UserData, DbErr> {
+        let mut active: ActiveModel = before.clone().into();
+        active.status = Set(UserStatus::Active);
+        active.deleted_at = Set(None);
+        active.update(db).await
+    }
+}